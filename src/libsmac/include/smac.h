@@ -0,0 +1,59 @@
+/* C interface to libsmac, the Smaragdine compiler front end.
+ *
+ * Link against the libsmac cdylib or staticlib, which are not built by
+ * default; build them from the workspace with
+ *
+ *     cargo rustc -p libsmac --release --crate-type cdylib
+ *     cargo rustc -p libsmac --release --crate-type staticlib
+ *
+ * Strings returned by these functions are owned by the compilation they
+ * came from and stay valid until it is freed.
+ */
+
+#ifndef SMAC_H
+#define SMAC_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Severities returned by smac_diagnostic_severity */
+#define SMAC_ERROR   0
+#define SMAC_WARNING 1
+
+/* The result of compiling one source */
+typedef struct smac_compilation smac_compilation;
+
+/* Lexes and parses the NUL-terminated UTF-8 `source`. Returns NULL if
+ * `source` is NULL or not UTF-8. */
+smac_compilation *smac_compile(const char *source);
+
+/* Releases a compilation. NULL is ignored. */
+void smac_compilation_free(smac_compilation *compilation);
+
+/* Number of top-level nodes parsed */
+size_t smac_node_count(const smac_compilation *compilation);
+
+size_t smac_diagnostic_count(const smac_compilation *compilation);
+
+/* SMAC_ERROR or SMAC_WARNING, or -1 if `index` is out of range */
+int smac_diagnostic_severity(const smac_compilation *compilation, size_t index);
+
+/* The message, or NULL if `index` is out of range */
+const char *smac_diagnostic_message(const smac_compilation *compilation, size_t index);
+
+/* The code, like "E0001", explained by `smac explain`, or NULL if the
+ * diagnostic has none or `index` is out of range */
+const char *smac_diagnostic_code(const smac_compilation *compilation, size_t index);
+
+/* 1-based position of the start of the diagnostic, or 0 if `index` is out of range */
+size_t smac_diagnostic_line(const smac_compilation *compilation, size_t index);
+size_t smac_diagnostic_column(const smac_compilation *compilation, size_t index);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SMAC_H */
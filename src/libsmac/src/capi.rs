@@ -0,0 +1,172 @@
+// The C interface declared in include/smac.h. Everything is reached
+// through an opaque `smac_compilation` handle, which owns the strings it
+// hands out until it is freed.
+
+use diagnostic::{Diagnostic, Severity};
+use parser::parse_source;
+
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, UnwindSafe};
+use std::ptr;
+
+/// The result of compiling one source
+pub struct Compilation {
+    nodes:       usize,
+    diagnostics: Vec<Diagnostic>,
+    messages:    Vec<CString>,
+    codes:       Vec<Option<CString>>,
+}
+
+impl Compilation {
+    fn diagnostic(&self, index: usize) -> Option<&Diagnostic> {
+        self.diagnostics.get(index)
+    }
+}
+
+/// Runs `f`, or gives `failed` if it panics, since unwinding into C is
+/// undefined behaviour
+fn guard<T, F: FnOnce() -> T + UnwindSafe>(failed: T, f: F) -> T {
+    panic::catch_unwind(f).unwrap_or(failed)
+}
+
+/// Lexes and parses the NUL-terminated UTF-8 `source`. Returns null if
+/// `source` is null or not UTF-8; otherwise the handle must be released
+/// with `smac_compilation_free`, and null is also returned if compiling
+/// panics.
+///
+/// # Safety
+///
+/// `source` must be null or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn smac_compile(source: *const c_char) -> *mut Compilation {
+    if source.is_null() {
+        return ptr::null_mut()
+    }
+
+    let source = match CStr::from_ptr(source).to_str() {
+        Ok(source) => source,
+        Err(_)     => return ptr::null_mut(),
+    };
+
+    guard(ptr::null_mut(), || {
+        let (nodes, diagnostics) = parse_source(source);
+        let messages = diagnostics.iter()
+                                  .map(|d| CString::new(d.message().replace('\0', "")).unwrap_or_default())
+                                  .collect();
+        let codes    = diagnostics.iter()
+                                  .map(|d| d.code().map(|c| CString::new(c.id()).unwrap()))
+                                  .collect();
+
+        Box::into_raw(Box::new(Compilation {
+            nodes:       nodes.len(),
+            diagnostics: diagnostics,
+            messages:    messages,
+            codes:       codes,
+        }))
+    })
+}
+
+/// Releases a handle from `smac_compile`. Null is ignored.
+///
+/// # Safety
+///
+/// `compilation` must be null or a handle from `smac_compile` that has
+/// not already been freed. It must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn smac_compilation_free(compilation: *mut Compilation) {
+    if !compilation.is_null() {
+        guard((), || drop(Box::from_raw(compilation)))
+    }
+}
+
+/// Number of top-level nodes parsed
+///
+/// # Safety
+///
+/// `compilation` must be null or a handle from `smac_compile` that has
+/// not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn smac_node_count(compilation: *const Compilation) -> usize {
+    guard(0, || compilation.as_ref().map_or(0, |c| c.nodes))
+}
+
+/// Number of diagnostics reported
+///
+/// # Safety
+///
+/// `compilation` must be null or a handle from `smac_compile` that has
+/// not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn smac_diagnostic_count(compilation: *const Compilation) -> usize {
+    guard(0, || compilation.as_ref().map_or(0, |c| c.diagnostics.len()))
+}
+
+/// `SMAC_ERROR` or `SMAC_WARNING`, or -1 if `index` is out of range
+///
+/// # Safety
+///
+/// `compilation` must be null or a handle from `smac_compile` that has
+/// not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn smac_diagnostic_severity(compilation: *const Compilation, index: usize) -> c_int {
+    guard(-1, || match compilation.as_ref().and_then(|c| c.diagnostic(index)).map(|d| d.severity()) {
+        Some(Severity::Error)   => 0,
+        Some(Severity::Warning) => 1,
+        None                    => -1,
+    })
+}
+
+/// The message, owned by the handle, or null if `index` is out of range
+///
+/// # Safety
+///
+/// `compilation` must be null or a handle from `smac_compile` that has
+/// not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn smac_diagnostic_message(compilation: *const Compilation, index: usize) -> *const c_char {
+    guard(ptr::null(), || {
+        compilation.as_ref()
+                   .and_then(|c| c.messages.get(index))
+                   .map_or(ptr::null(), |m| m.as_ptr())
+    })
+}
+
+/// The code, like `E0001`, or null if the diagnostic has none or `index`
+/// is out of range
+///
+/// # Safety
+///
+/// `compilation` must be null or a handle from `smac_compile` that has
+/// not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn smac_diagnostic_code(compilation: *const Compilation, index: usize) -> *const c_char {
+    guard(ptr::null(), || {
+        compilation.as_ref()
+                   .and_then(|c| c.codes.get(index))
+                   .and_then(|c| c.as_ref())
+                   .map_or(ptr::null(), |c| c.as_ptr())
+    })
+}
+
+/// 1-based line of the start of the diagnostic, or 0 if `index` is out of range
+///
+/// # Safety
+///
+/// `compilation` must be null or a handle from `smac_compile` that has
+/// not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn smac_diagnostic_line(compilation: *const Compilation, index: usize) -> usize {
+    guard(0, || compilation.as_ref().and_then(|c| c.diagnostic(index)).map_or(0, |d| d.position().line))
+}
+
+/// 1-based column of the start of the diagnostic, or 0 if `index` is out of range
+///
+/// # Safety
+///
+/// `compilation` must be null or a handle from `smac_compile` that has
+/// not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn smac_diagnostic_column(compilation: *const Compilation, index: usize) -> usize {
+    guard(0, || compilation.as_ref().and_then(|c| c.diagnostic(index)).map_or(0, |d| d.position().col + 1))
+}
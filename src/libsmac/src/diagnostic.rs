@@ -0,0 +1,231 @@
+use lexer::token::TokenPosition;
+
+use std::fmt;
+
+/// How serious a diagnostic is
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Severity::Error   => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Declares `Code` and `CODES` from one list, so neither can miss a code
+macro_rules! codes {
+    ($($code:ident,)+) => {
+        /// The kinds of error and lint warning smac reports, each explained
+        /// by `smac explain`
+        #[derive(Debug, Copy, Clone, PartialEq)]
+        pub enum Code {
+            $($code,)+
+        }
+
+        /// Every code, in order
+        pub const CODES: &'static [Code] = &[
+            $(Code::$code,)+
+        ];
+    };
+}
+
+codes! {
+    UnexpectedCharacter,
+    IntegerTooLarge,
+    UnexpectedDecimalPoint,
+    InvalidEscape,
+    UnterminatedLiteral,
+    CharLength,
+    UnexpectedToken,
+    Expected,
+    Unclosed,
+    TooDeep,
+    FloatTooLarge,
+    ExpectedDigits,
+    NonSnakeCase,
+    SuspiciousComparison,
+    Shadowing,
+    MagicNumber,
+}
+
+/// The extended description of a code, with a source that raises it and
+/// the same source fixed
+#[derive(Debug, Copy, Clone)]
+pub struct Explanation {
+    pub id:          &'static str,
+    pub summary:     &'static str,
+    pub description: &'static str,
+    pub example:     &'static str,
+    pub fix:         &'static str,
+}
+
+impl Code {
+    /// The code with the id `id`, like `E0001`
+    pub fn from_id(id: &str) -> Option<Code> {
+        CODES.iter().cloned().find(|c| c.id().eq_ignore_ascii_case(id))
+    }
+
+    pub fn id(&self) -> &'static str {
+        self.explanation().id
+    }
+
+    /// Errors have `E` codes, lint warnings `W` codes
+    pub fn severity(&self) -> Severity {
+        if self.id().starts_with('W') { Severity::Warning } else { Severity::Error }
+    }
+
+    pub fn explanation(&self) -> Explanation {
+        let (id, summary, description, example, fix) = match *self {
+            Code::UnexpectedCharacter => ("E0001", "unexpected character",
+                "A character that can't start any token was found. It is skipped.",
+                "[1, 2] @", "[1, 2]"),
+            Code::IntegerTooLarge => ("E0002", "integer literal too large",
+                "Integers are 32 bits, so an integer literal must be at most 2147483647.",
+                "4294967296", "2147483647"),
+            Code::UnexpectedDecimalPoint => ("E0003", "unexpected decimal point",
+                "A float literal has a single decimal point. The lexer ends the literal\n\
+                 at the second one and starts a new token after it.",
+                "1.2.3", "1.23"),
+            Code::InvalidEscape => ("E0004", "invalid character escape",
+                "Only \\\\, \\', \\\", \\n, \\r and \\t are escapes in string and char literals.\n\
+                 To write a backslash, escape it, or use a raw string.",
+                "\"\\q\"", "\"\\\\q\""),
+            Code::UnterminatedLiteral => ("E0005", "unterminated literal",
+                "A string or char literal is missing its closing quote, so it runs to\n\
+                 the end of the source.",
+                "\"open", "\"open\""),
+            Code::CharLength => ("E0006", "char literal must contain exactly one character",
+                "A char literal holds one character. Use a string for more, or none.",
+                "'ab'", "\"ab\""),
+            Code::UnexpectedToken => ("E0007", "unexpected token",
+                "The token can't start an expression here. It is skipped.",
+                "1)", "1"),
+            Code::Expected => ("E0008", "expected something else in brackets",
+                "The elements of lists and maps, and indexes, are expressions\n\
+                 separated by commas. Map entries are written `key: value`.",
+                "[1 2] {1}", "[1, 2] {1: 2}"),
+            Code::Unclosed => ("E0009", "unclosed bracket",
+                "The source ended before the bracket opening a list, map or index\n\
+                 was closed.",
+                "[1, 2", "[1, 2]"),
+            Code::TooDeep => ("E0010", "brackets nested too deeply",
+                "Lists, maps and indexes may nest at most 256 deep. Deeper brackets\n\
+                 are skipped up to their closing bracket.",
+                "[[[[ ... 257 levels ... ]]]]", "[[[[ ... 256 levels ... ]]]]"),
+            Code::FloatTooLarge => ("E0011", "float literal too large",
+                "Floats are 32 bits, so a float literal must be below about 3.4e38.",
+                "1000000000000000000000000000000000000000.0", "100000000000000000000000000000000000000.0"),
+            Code::ExpectedDigits => ("E0012", "expected digits after a base prefix",
+                "A hexadecimal literal starts with `0x` and a binary one with `0b`,\n\
+                 and either needs at least one digit after its prefix.",
+                "0x", "0x0"),
+            Code::NonSnakeCase => ("W0001", "name should be snake_case",
+                "Bound names and parameters are written in lowercase, with words\n\
+                 separated by underscores. Lint rule `naming`.",
+                "let fooBar = 1", "let foo_bar = 1"),
+            Code::SuspiciousComparison => ("W0002", "suspicious comparison",
+                "Comparing a value with itself always gives the same result, and\n\
+                 comparing with `true` or `false` can be left out. Lint rule\n\
+                 `suspicious_comparison`.",
+                "let same = x == x", "let same = x == y"),
+            Code::Shadowing => ("W0003", "binding shadows an earlier one",
+                "A `let` reuses a name bound earlier in the file, hiding the earlier\n\
+                 binding. Lint rule `shadowing`.",
+                "let x = 1\nlet x = 0", "let x = 1\nlet y = 0"),
+            Code::MagicNumber => ("W0004", "magic number",
+                "A numeric literal other than 0 and 1 is used without a name saying\n\
+                 what it means. Bind it with `let` first. Lint rule `magic_numbers`.",
+                "let area = r * r * 3.14", "let pi = 3.14\nlet area = r * r * pi"),
+        };
+
+        Explanation {
+            id:          id,
+            summary:     summary,
+            description: description,
+            example:     example,
+            fix:         fix,
+        }
+    }
+}
+
+/// A problem found in the source, spanning from `position` up to `end`
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    severity: Severity,
+    code: Option<Code>,
+    message: String,
+    position: TokenPosition,
+    end: TokenPosition,
+}
+
+impl Diagnostic {
+    /// Creates an error
+    pub fn new(code: Code, message: String, position: TokenPosition, end: TokenPosition) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            code: Some(code),
+            message: message,
+            position: position,
+            end: end,
+        }
+    }
+
+    /// Creates a warning. Warnings come from lint rules, which also
+    /// name themselves in the message.
+    pub fn warning(code: Code, message: String, position: TokenPosition, end: TokenPosition) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            code: Some(code),
+            message: message,
+            position: position,
+            end: end,
+        }
+    }
+
+    // Immutable access
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn code(&self) -> Option<Code> {
+        self.code
+    }
+
+    pub fn message(&self) -> &String {
+        &self.message
+    }
+
+    pub fn position(&self) -> &TokenPosition {
+        &self.position
+    }
+
+    pub fn end(&self) -> &TokenPosition {
+        &self.end
+    }
+
+    // Mutable access
+    pub fn position_mut(&mut self) -> &mut TokenPosition {
+        &mut self.position
+    }
+
+    pub fn end_mut(&mut self) -> &mut TokenPosition {
+        &mut self.end
+    }
+}
+
+/// Formats as `line:col: severity[code]: message`, with a 1-based column
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.position.line, self.position.col + 1, self.severity)?;
+        if let Some(code) = self.code {
+            write!(f, "[{}]", code.id())?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
@@ -0,0 +1,334 @@
+use diagnostic::Diagnostic;
+use lexer::{Lexer, Token, TokenType};
+use lexer::block_tree::{BlockTree, Branch, ChunkValue};
+use lexer::token::TokenPosition;
+
+/// Settings for `format`
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Spaces per block level
+    pub indent_width: usize,
+    /// Width lines are broken after commas to fit in, where they can be
+    pub max_line_length: usize,
+    /// Whether lists and maps broken over lines end in a comma. Others
+    /// never do.
+    pub trailing_comma: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            indent_width:    4,
+            max_line_length: 100,
+            trailing_comma:  false,
+        }
+    }
+}
+
+/// A non-empty source line and the block holding it
+struct Line<'a> {
+    /// Number of blocks the line is nested in
+    depth: usize,
+    /// Line number and indentation in the source
+    position: TokenPosition,
+    text: &'a str,
+    /// Whether the line comes right after another line of its block,
+    /// rather than after a nested block
+    follows: bool,
+}
+
+/// Formats `source` canonically: blocks are indented by `indent_width`
+/// spaces per level, tokens are separated by single spaces where they
+/// need spacing at all, and runs of blank lines are collapsed to one.
+/// Brackets left open at the end of a line continue it on the next line
+/// of the same block, and such lines are joined and broken again after
+/// commas to fit in `max_line_length`. Trailing commas follow
+/// `trailing_comma`.
+///
+/// Sources that don't lex cleanly are not formatted; their errors are
+/// returned instead.
+pub fn format(source: &str, config: &Config) -> Result<String, Vec<Diagnostic>> {
+    let mut block_tree = BlockTree::new(source, 0);
+    let indents        = block_tree.collect_indents();
+    let tree           = block_tree.tree(&indents);
+    let mut lines      = Vec::new();
+
+    // Two blocks in a row would be merged into one by indenting them alike,
+    // so then the source's indentation is kept
+    let siblings = collect_lines(&tree, 0, &mut lines);
+
+    let mut diagnostics = Vec::new();
+    let mut tokens      = Vec::new();
+
+    for line in &lines {
+        let (found_tokens, found) = Lexer::tokenize(line.text);
+
+        for mut d in found {
+            *d.position_mut() = d.position().offset(&line.position);
+            *d.end_mut()      = d.end().offset(&line.position);
+            diagnostics.push(d)
+        }
+        tokens.push(found_tokens)
+    }
+
+    if !diagnostics.is_empty() {
+        return Err(diagnostics)
+    }
+
+    let mut formatted = String::new();
+    let mut last_line = None;
+    let mut i         = 0;
+
+    while i < lines.len() {
+        let first    = i;
+        let mut open = brackets(&tokens[i]);
+
+        while open > 0 && i + 1 < lines.len() && lines[i + 1].follows {
+            i += 1;
+            open += brackets(&tokens[i]);
+        }
+
+        let position = lines[first].position;
+        let indent   = if siblings { position.col } else { lines[first].depth * config.indent_width };
+        let texts    = lines[first..i + 1].iter().map(|l| l.text).collect::<Vec<_>>();
+
+        if last_line.map_or(false, |last| position.line > last + 1) {
+            formatted.push('\n');
+        }
+        last_line = Some(lines[i].position.line);
+
+        for line in format_lines(&texts, indent, config) {
+            for _ in 0..indent {
+                formatted.push(' ');
+            }
+            formatted.push_str(&line);
+            formatted.push('\n');
+        }
+
+        i += 1
+    }
+
+    Ok(formatted)
+}
+
+/// Appends the lines of `branch`, `depth` blocks deep, to `lines`. Returns
+/// whether two blocks follow each other anywhere in it.
+fn collect_lines<'a>(branch: &'a Branch, depth: usize, lines: &mut Vec<Line<'a>>) -> bool {
+    let mut siblings = false;
+    let mut follows  = false;
+
+    for (i, chunk) in branch.value.iter().enumerate() {
+        match *chunk.value() {
+            ChunkValue::Source(ref text) => {
+                lines.push(Line { depth: depth, position: *chunk.position(), text: text, follows: follows });
+                follows = true
+            },
+            ChunkValue::Block(ref block) => {
+                // A branch starts with a line, so a block after anything
+                // but a line comes after another block
+                siblings |= i > 0 && !follows;
+                siblings |= collect_lines(block, depth + 1, lines);
+                follows   = false
+            },
+            _ => (),
+        }
+    }
+
+    siblings
+}
+
+/// How many more brackets `tokens` open than they close
+fn brackets(tokens: &[Token]) -> isize {
+    tokens.iter()
+          .map(|t| match symbol(t) {
+              "(" | "[" | "{" => 1,
+              ")" | "]" | "}" => -1,
+              _               => 0,
+          })
+          .sum()
+}
+
+/// Respaces the tokens of `lines`, which continue one another, as a
+/// single line and breaks it after commas where it doesn't fit. The
+/// lines are kept as they are if respacing would make them lex
+/// differently.
+fn format_lines(lines: &[&str], indent: usize, config: &Config) -> Vec<String> {
+    let line  = lines.join(" ");
+    let chars = line.chars().collect::<Vec<char>>();
+    let text  = |t: &Token| chars[t.position().col..t.end().col].iter().cloned().collect::<String>();
+
+    // Trailing commas are dropped, and put back below where they're wanted
+    let lexed  = Lexer::tokenize(&line).0;
+    let tokens = lexed.iter()
+                      .enumerate()
+                      .filter(|&(i, t)| {
+                          !(symbol(t) == "," && i > 0 && !["[", "{", ","].contains(&symbol(&lexed[i - 1]))
+                            && lexed.get(i + 1).map_or(false, |next| ["]", "}"].contains(&symbol(next))))
+                      })
+                      .map(|(_, t)| t.clone())
+                      .collect::<Vec<_>>();
+
+    // Each token's text, with whether a space goes before it
+    let mut pieces = tokens.iter()
+                           .enumerate()
+                           .map(|(i, t)| (i > 0 && spaced(&chars, &tokens, i), text(t)))
+                           .collect::<Vec<_>>();
+
+    let formatted = pieces.iter()
+                          .map(|&(space, ref text)| if space { format!(" {}", text) } else { text.clone() })
+                          .collect::<String>();
+
+    let relexed = Lexer::tokenize(&formatted).0;
+    let same    = relexed.len() == tokens.len()
+        && relexed.iter().zip(&tokens).all(|(a, b)| a == b && a.content() == b.content());
+
+    if !same {
+        return lines.iter().map(|l| l.to_string()).collect()
+    }
+
+    // The output line of each token, filled with runs of tokens ending in
+    // a comma while they fit
+    let mut rows  = vec![0; pieces.len()];
+    let mut row   = 0;
+    let mut width = indent;
+    let mut start = 0;
+
+    for end in 0..pieces.len() {
+        if end + 1 < pieces.len() && symbol(&tokens[end]) != "," {
+            continue
+        }
+
+        let run = (start..end + 1).map(|i| pieces[i].0 as usize + pieces[i].1.chars().count()).sum::<usize>();
+
+        if start > 0 && width + run > config.max_line_length {
+            row  += 1;
+            width = indent + run - pieces[start].0 as usize;
+        } else {
+            width += run;
+        }
+        for i in start..end + 1 {
+            rows[i] = row;
+        }
+        start = end + 1;
+    }
+
+    if config.trailing_comma {
+        let mut open = Vec::new();
+
+        for i in 0..tokens.len() {
+            match symbol(&tokens[i]) {
+                "(" | "[" | "{" => open.push(i),
+                ")" | "]" | "}" => {
+                    // Lists and maps that are broken over lines and not empty
+                    let opened = open.pop();
+                    if opened.map_or(false, |o| rows[o] < rows[i] && o + 1 < i && symbol(&tokens[o]) != "(")
+                        && symbol(&tokens[i - 1]) != "," {
+                        pieces[i - 1].1.push(',');
+                    }
+                },
+                _ => (),
+            }
+        }
+    }
+
+    let mut broken: Vec<String> = Vec::new();
+
+    for (i, (space, text)) in pieces.into_iter().enumerate() {
+        if i == 0 || rows[i] > rows[i - 1] {
+            broken.push(text)
+        } else if let Some(line) = broken.last_mut() {
+            if space {
+                line.push(' ');
+            }
+            line.push_str(&text)
+        }
+    }
+
+    broken
+}
+
+/// The content of `t` if it is a symbol, or nothing
+fn symbol(t: &Token) -> &str {
+    if *t.token_type() == TokenType::Symbol { t.content() } else { "" }
+}
+
+/// Whether a space goes between `tokens[i - 1]` and `tokens[i]`
+fn spaced(chars: &[char], tokens: &[Token], i: usize) -> bool {
+    let prev = &tokens[i - 1];
+    let cur  = &tokens[i];
+
+    let is = |t: &Token, token_type: TokenType, content: &[&str]| {
+        *t.token_type() == token_type && content.contains(&t.content().as_str())
+    };
+
+    // Inside brackets
+    if is(cur, TokenType::Symbol, &[")", "]", "}"]) || is(prev, TokenType::Symbol, &["(", "[", "{"]) {
+        return false
+    }
+
+    // Separators
+    if is(cur, TokenType::Symbol, &[","]) {
+        return false
+    }
+
+    // Calls and indexing, which touch what they apply to
+    if is(cur, TokenType::Symbol, &["(", "["]) && prev.end().col == cur.position().col
+        && (*prev.token_type() == TokenType::Identifier || is(prev, TokenType::Symbol, &[")", "]"])) {
+        return false
+    }
+
+    // Type hints
+    if is(cur, TokenType::Operator, &[":"]) {
+        return false
+    }
+
+    // Unary operators
+    if is(prev, TokenType::Operator, &["-", "+", "~", "!"]) {
+        let unary = i == 1 || {
+            let before = &tokens[i - 2];
+            match *before.token_type() {
+                TokenType::Operator | TokenType::Keyword => true,
+                _                                        => is(before, TokenType::Symbol, &["(", "[", "{", ","]),
+            }
+        };
+
+        if unary {
+            return false
+        }
+    }
+
+    // A unary operator after another, as in `x = -1`
+    if is(cur, TokenType::Operator, &["-", "+", "~", "!"]) && *prev.token_type() == TokenType::Operator {
+        return true
+    }
+
+    // Tokens that touch in the source and split a run of word or
+    // operator characters, like `->`, stay together
+    if prev.end().col == cur.position().col {
+        let last  = chars[prev.end().col - 1];
+        let first = chars[cur.position().col];
+
+        if class(last) != Class::Other && class(last) == class(first) {
+            return false
+        }
+    }
+
+    true
+}
+
+#[derive(PartialEq)]
+enum Class {
+    Word,
+    Operator,
+    Other,
+}
+
+fn class(c: char) -> Class {
+    if c.is_alphanumeric() || "_?!".contains(c) {
+        Class::Word
+    } else if "+-*/=<>~&^|%:".contains(c) {
+        Class::Operator
+    } else {
+        Class::Other
+    }
+}
@@ -0,0 +1,94 @@
+use lexer::{SYMBOLS, OPERATORS, KEYWORDS, BOOLEANS};
+
+/// A definition `name = alternatives[0] | alternatives[1] | ...`, with the
+/// alternatives in the notation of grammar.md
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub name:         &'static str,
+    pub alternatives: Vec<String>,
+}
+
+impl Rule {
+    fn new(name: &'static str, alternatives: &[&str]) -> Rule {
+        Rule {
+            name:         name,
+            alternatives: alternatives.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    fn terminals(name: &'static str, terminals: &[&str]) -> Rule {
+        Rule {
+            name:         name,
+            alternatives: terminals.iter().map(|t| terminal(t)).collect(),
+        }
+    }
+}
+
+/// `t` as a terminal string
+fn terminal(t: &str) -> String {
+    if t.contains('\'') {
+        format!("\"{}\"", t)
+    } else {
+        format!("'{}'", t)
+    }
+}
+
+/// The grammar as implemented: tokens as the lexer matches them, built
+/// from its tables, and the sequence of expressions the parser accepts.
+/// grammar.md describes the language being worked towards.
+pub fn rules() -> Vec<Rule> {
+    vec![
+        Rule::new("digit", &["'0'...'9'"]),
+        Rule::new("digit_hex", &["digit", "'a'...'f'", "'A'...'F'"]),
+        Rule::new("digit_bin", &["'0'", "'1'"]),
+        Rule::new("char", &["(* Any UTF-8 code-point *)"]),
+        Rule::new("letter", &["(* Any alphabetic code-point *)"]),
+        Rule::new("identifier", &["( letter | '_' ) { letter | digit | '_' | '!' | '?' }"]),
+
+        Rule::terminals("keyword", KEYWORDS),
+        Rule::terminals("operator", OPERATORS),
+        Rule::terminals("symbol", SYMBOLS),
+
+        Rule::terminals("literal_boolean", BOOLEANS),
+        Rule::new("literal_integer", &["'0x' digit_hex { digit_hex }",
+                                       "'0b' digit_bin { digit_bin }",
+                                       "digit { digit }"]),
+        Rule::new("literal_float", &["digit { digit } '.' { digit }", "'.' { digit }"]),
+        Rule::new("escape", &["'\\' ( '\\' | \"'\" | '\"' | 'n' | 'r' | 't' )"]),
+        Rule::new("literal_char", &["\"'\" ( char | escape ) \"'\""]),
+        Rule::new("literal_string", &["'\"' { char | escape } '\"'"]),
+        Rule::new("literal_raw_string", &["'r\"' { char } '\"'"]),
+        Rule::new("literal", &["literal_boolean",
+                               "literal_integer",
+                               "literal_float",
+                               "literal_char",
+                               "literal_string",
+                               "literal_raw_string"]),
+
+        Rule::new("list", &["'[' [ expression { ',' expression } [ ',' ] ] ']'"]),
+        Rule::new("entry", &["expression ':' expression"]),
+        Rule::new("map", &["'{' [ entry { ',' entry } [ ',' ] ] '}'"]),
+        Rule::new("primary", &["literal", "list", "map"]),
+        Rule::new("expression", &["primary { '[' expression ']' } (* no space before '[' *)"]),
+
+        Rule::new("program", &["{ expression }"]),
+    ]
+}
+
+/// Renders `rules` as EBNF, laid out like grammar.md
+pub fn ebnf(rules: &[Rule]) -> String {
+    let width   = rules.iter().map(|r| r.name.len()).max().unwrap_or(0);
+    let mut out = String::new();
+
+    for rule in rules {
+        for (i, alternative) in rule.alternatives.iter().enumerate() {
+            if i == 0 {
+                out.push_str(&format!("{:<width$} = {}\n", rule.name, alternative, width = width));
+            } else {
+                out.push_str(&format!("{:<width$} | {}\n", "", alternative, width = width));
+            }
+        }
+    }
+
+    out
+}
@@ -0,0 +1,86 @@
+use lexer::{grab_smaragdine_lexer, TokenType};
+
+/// What a piece of source is highlighted as
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Group {
+    Keyword,
+    Boolean,
+    Number,
+    String,
+    Char,
+    Operator,
+    Punctuation,
+    Identifier,
+}
+
+impl Group {
+    /// Name of the group, as used for CSS classes
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Group::Keyword     => "keyword",
+            Group::Boolean     => "boolean",
+            Group::Number      => "number",
+            Group::String      => "string",
+            Group::Char        => "char",
+            Group::Operator    => "operator",
+            Group::Punctuation => "punctuation",
+            Group::Identifier  => "identifier",
+        }
+    }
+
+    fn of(token_type: &TokenType) -> Option<Group> {
+        match *token_type {
+            TokenType::Keyword                                          => Some(Group::Keyword),
+            TokenType::BooleanLiteral                                   => Some(Group::Boolean),
+            TokenType::IntLiteral | TokenType::FloatLiteral             => Some(Group::Number),
+            TokenType::StringLiteral | TokenType::LiteralStringLiteral  => Some(Group::String),
+            TokenType::CharLiteral                                      => Some(Group::Char),
+            TokenType::Operator                                         => Some(Group::Operator),
+            TokenType::Symbol                                           => Some(Group::Punctuation),
+            TokenType::Identifier                                       => Some(Group::Identifier),
+            _                                                           => None,
+        }
+    }
+}
+
+/// Splits `source` into pieces and the group each is highlighted as.
+/// Joined together the pieces are exactly `source`; whitespace and
+/// characters that don't lex are in no group.
+pub fn highlight(source: &str) -> Vec<(Option<Group>, String)> {
+    let chars = source.chars().collect::<Vec<char>>();
+
+    // Index of the first char of every line
+    let mut lines = vec![0];
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '\n' {
+            lines.push(i + 1)
+        }
+    }
+
+    let offset = |line: usize, col: usize| {
+        lines.get(line - 1).map_or(chars.len(), |start| (start + col).min(chars.len()))
+    };
+
+    let mut pieces = Vec::new();
+    let mut last   = 0;
+
+    for t in grab_smaragdine_lexer(&mut source.chars()) {
+        let start = offset(t.position().line, t.position().col);
+        let end   = offset(t.end().line, t.end().col);
+
+        if start < last {
+            continue
+        }
+        if start > last {
+            pieces.push((None, chars[last..start].iter().cloned().collect()));
+        }
+        pieces.push((Group::of(t.token_type()), chars[start..end].iter().cloned().collect()));
+        last = end;
+    }
+
+    if last < chars.len() {
+        pieces.push((None, chars[last..].iter().cloned().collect()));
+    }
+
+    pieces
+}
@@ -1,4 +1,5 @@
 use lexer::Token;
+use lexer::token::TokenPosition;
 
 #[derive(Debug)]
 pub enum ChunkValue {
@@ -10,18 +11,25 @@ pub enum ChunkValue {
 #[derive(Debug)]
 pub struct Chunk {
     value: ChunkValue,
+    position: TokenPosition,
 }
 
 impl Chunk {
-    pub fn new(value: ChunkValue) -> Chunk {
+    pub fn new(value: ChunkValue, position: TokenPosition) -> Chunk {
         Chunk {
             value: value,
+            position: position,
         }
     }
 
     pub fn value(&self) -> &ChunkValue {
         &self.value
     }
+
+    /// Line and indentation the chunk starts at
+    pub fn position(&self) -> &TokenPosition {
+        &self.position
+    }
 }
 
 #[derive(Debug)]
@@ -51,13 +59,15 @@ impl<'a> BlockTree<'a> {
         }
     }
 
-    pub fn collect_indents(&self) -> Vec<(usize, &'a str)> {
+    /// Collects the non-empty lines of the source, positioned
+    /// at their line number and indentation.
+    pub fn collect_indents(&self) -> Vec<(TokenPosition, &'a str)> {
         let mut indents = Vec::new();
-        let mut lines   = self.source.lines();
+        let mut lines   = self.source.lines().enumerate();
 
-        while let Some(line) = lines.next() {
+        while let Some((number, line)) = lines.next() {
             if line.trim().len() > 0 {
-                indents.push((self.indent(&line), line.trim()))
+                indents.push((TokenPosition::new(number + 1, self.indent(&line)), line.trim()))
             } 
         }
 
@@ -78,21 +88,22 @@ impl<'a> BlockTree<'a> {
         pos
     }
 
-    pub fn tree(&mut self, indents: &Vec<(usize, &'a str)>) -> Branch {
+    pub fn tree(&mut self, indents: &Vec<(TokenPosition, &'a str)>) -> Branch {
         let mut branch = Branch::new(Vec::new());
         let line       = indents.get(self.current_line);
 
-        let &(base_indent, _) = match line {
-            Some(i) => i,
-            None    => return branch,
+        let base_indent = match line {
+            Some(&(position, _)) => position.col,
+            None                 => return branch,
         };
 
         while self.current_line < indents.len() {
-            let (indent, line) = indents[self.current_line];
+            let (position, line) = indents[self.current_line];
+            let indent           = position.col;
 
             if indent == base_indent {
 
-                branch.value.push(Chunk::new(ChunkValue::Source(line.to_owned())))
+                branch.value.push(Chunk::new(ChunkValue::Source(line.to_owned()), position))
             
             } else if indent < base_indent {
             
@@ -101,7 +112,7 @@ impl<'a> BlockTree<'a> {
                 return branch
 
             } else if indent > base_indent {
-                branch.value.push(Chunk::new(ChunkValue::Block(self.tree(&indents))))
+                branch.value.push(Chunk::new(ChunkValue::Block(self.tree(&indents)), position))
             }
 
             self.current_line += 1
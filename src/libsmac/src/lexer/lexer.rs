@@ -1,56 +1,88 @@
+use diagnostic::{Code, Diagnostic};
 use lexer::Tokenizer;
 use lexer::matcher::*;
-use lexer::token::{Token, TokenType, TokenPosition};
+use lexer::token::{Token, TokenType};
 use lexer::block_tree::{Branch, Chunk, ChunkValue};
 
+use std::rc::Rc;
 use std::str::Chars;
 
+/// Symbols recognised by the Smaragdine lexer
+pub const SYMBOLS: &'static [&'static str] = &[
+    "(",
+    ")",
+    "[",
+    "]",
+    "{",
+    "}",
+    ",",
+    "->",
+];
+
+/// Operators recognised by the Smaragdine lexer. Longer operators
+/// come before their prefixes, as the first match wins.
+pub const OPERATORS: &'static [&'static str] = &[
+    "+"   // add
+    ,"-"  // sub
+    ,"*"  // mul
+    ,"/"  // div
+    ,"==" // equality
+    ,"="  // assignment
+    ,"<<" // shift left
+    ,"<=" // less than or equal
+    ,"<"  // less than
+    ,">>" // shift right
+    ,">=" // greater than or equal
+    ,">"  // greater than
+    ,"~"  // bitwise negate
+    ,"!"  // logical not
+    ,"&&" // logical and
+    ,"&"  // bitwise and
+    ,"^"  // bitwise xor
+    ,"||" // logical or
+    ,"|"  // bitwise or
+    ,"%"  // modulo
+    ,":"  // type hint
+];
+
+/// Keywords recognised by the Smaragdine lexer
+pub const KEYWORDS: &'static [&'static str] = &[
+    "let",
+    "if",
+    "else",
+];
+
+/// Boolean literals recognised by the Smaragdine lexer
+pub const BOOLEANS: &'static [&'static str] = &[
+    "true",
+    "false",
+];
+
+thread_local! {
+    /// The dispatch index of the Smaragdine matchers, which is the same
+    /// for every lexer `grab_smaragdine_lexer` returns
+    static SMARAGDINE_DISPATCH: Rc<Vec<Vec<usize>>> = {
+        let lexer = smaragdine_lexer(&mut "".chars());
+        Rc::new(lexer.build_dispatch())
+    };
+}
+
 pub fn grab_smaragdine_lexer(data: &mut Chars) -> Lexer {
+    let mut lexer = smaragdine_lexer(data);
+    lexer.dispatch = Some(SMARAGDINE_DISPATCH.with(|d| d.clone()));
+
+    lexer
+}
+
+/// A lexer with the Smaragdine matchers registered, but no index
+fn smaragdine_lexer(data: &mut Chars) -> Lexer {
     let tokenizer = Tokenizer::new(data);
     let mut lexer = Lexer::new(tokenizer);
 
-    let symbols = vec![
-        "(",
-        ")",
-        "[",
-        "]",
-        "->",
-    ].iter().map(|&x| x.to_string()).collect();
-
-    let operators = vec![
-        "+"   // add
-        ,"-"  // sub
-        ,"*"  // mul
-        ,"/"  // div
-        ,"==" // equality
-        ,"="  // assignment
-        ,"<<" // shift left
-        ,"<=" // less than or equal
-        ,"<"  // less than
-        ,">>" // shift right
-        ,">=" // greater than or equal
-        ,">"  // greater than
-        ,"~"  // bitwise negate
-        ,"!"  // logical not
-        ,"&&" // logical and
-        ,"&"  // bitwise and
-        ,"^"  // bitwise xor
-        ,"||" // logical or
-        ,"|"  // bitwise or
-        ,"%"  // modulo
-        ,":"  // type hint
-    ].iter().map(|&x| x.to_string()).collect();
-
-    let keywords = vec![
-        "let",
-        "if",
-        "else",
-    ].iter().map(|&x| x.to_string()).collect();
-
-    let boolean = vec![
-        "true",
-        "false",
-    ].iter().map(|&x| x.to_string()).collect();
+    let symbols   = SYMBOLS.iter().map(|&x| x.to_string()).collect();
+    let operators = OPERATORS.iter().map(|&x| x.to_string()).collect();
+    let keywords  = KEYWORDS.iter().map(|&x| x.to_string()).collect();
+    let boolean   = BOOLEANS.iter().map(|&x| x.to_string()).collect();
 
     let matcher_symbol         = ConstantMatcher::new(TokenType::Symbol, symbols);
     let matcher_operator       = ConstantMatcher::new(TokenType::Operator, operators);
@@ -75,19 +107,35 @@ pub fn grab_smaragdine_lexer(data: &mut Chars) -> Lexer {
     lexer
 }
 
-pub fn lex_branch(branch: &Branch) -> Branch {
+/// Lexes every source chunk of `branch`, collecting the errors
+/// found into `diagnostics`. Positions are made absolute using
+/// the position each chunk was found at.
+pub fn lex_branch(branch: &Branch, diagnostics: &mut Vec<Diagnostic>) -> Branch {
     let mut lexed_branch = Branch::new(Vec::new());
 
     for c in branch.value.iter() {
         match c.value() {
             &ChunkValue::Source(ref s) => {
-                let chunk = ChunkValue::Tokens(grab_smaragdine_lexer(&mut s.clone().chars()).collect());
-                lexed_branch.value.push(Chunk::new(chunk))
+                let (mut tokens, found) = Lexer::tokenize(s);
+
+                for t in tokens.iter_mut() {
+                    *t.position_mut() = t.position().offset(c.position());
+                    *t.end_mut()      = t.end().offset(c.position());
+                }
+
+                for mut d in found {
+                    *d.position_mut() = d.position().offset(c.position());
+                    *d.end_mut()      = d.end().offset(c.position());
+                    diagnostics.push(d)
+                }
+
+                let chunk = ChunkValue::Tokens(tokens);
+                lexed_branch.value.push(Chunk::new(chunk, *c.position()))
             },
 
             &ChunkValue::Block(ref b) => {
-                let chunk = ChunkValue::Block(lex_branch(&b));
-                lexed_branch.value.push(Chunk::new(chunk))
+                let chunk = ChunkValue::Block(lex_branch(&b, diagnostics));
+                lexed_branch.value.push(Chunk::new(chunk, *c.position()))
             },
 
             _ => (),
@@ -103,7 +151,7 @@ pub fn flatten_branch(branch: &Branch) -> Vec<Token> {
     for c in branch.value.iter() {
         match c.value() {
             &ChunkValue::Tokens(ref t) => flat.append(&mut t.clone()),
-            &ChunkValue::Block(ref b)  => flat.push(Token::new(TokenType::Block(flatten_branch(b)), TokenPosition::new(0, 0), "".to_string())),
+            &ChunkValue::Block(ref b)  => flat.push(Token::new(TokenType::Block(flatten_branch(b)), *c.position(), "".to_string())),
             _ => continue,
         }
     }
@@ -111,9 +159,29 @@ pub fn flatten_branch(branch: &Branch) -> Vec<Token> {
     flat
 }
 
+/// Characters below this get an entry in the dispatch index
+const DISPATCH_SIZE: usize = 128;
+
+/// The first token `matchers` match at the tokenizer's position
+fn first_match<'a, I>(tokenizer: &mut Tokenizer, matchers: I) -> Option<Token>
+    where I: Iterator<Item = &'a Box<Matcher>>
+{
+    for matcher in matchers {
+        match tokenizer.try_match_token(matcher.as_ref()) {
+            Some(t) => return Some(t),
+            None    => continue,
+        }
+    }
+    None
+}
+
 pub struct Lexer {
     tokenizer: Tokenizer,
     matchers: Vec<Box<Matcher>>,
+    /// For each ASCII character, the indices of the matchers that can
+    /// start with it. Built when first used after the matchers change,
+    /// and shared between lexers with the same matchers.
+    dispatch: Option<Rc<Vec<Vec<usize>>>>,
 }
 
 impl Lexer {
@@ -121,17 +189,62 @@ impl Lexer {
         Lexer {
             tokenizer: tokenizer,
             matchers: Vec::new(),
+            dispatch: None,
         }
     }
 
+    /// Lexes all of `source` with the Smaragdine matchers, carrying on
+    /// past errors, and returns the tokens along with every error found.
+    pub fn tokenize(source: &str) -> (Vec<Token>, Vec<Diagnostic>) {
+        let mut lexer = grab_smaragdine_lexer(&mut source.chars());
+        let tokens    = lexer.by_ref().collect();
+
+        (tokens, lexer.diagnostics().clone())
+    }
+
+    fn build_dispatch(&self) -> Vec<Vec<usize>> {
+        (0..DISPATCH_SIZE as u8).map(|c| {
+            (0..self.matchers.len()).filter(|&i| self.matchers[i].can_start(c as char)).collect()
+        }).collect()
+    }
+
     pub fn match_token(&mut self) -> Option<Token> {
-        for matcher in &mut self.matchers {
-            match self.tokenizer.try_match_token(matcher.as_ref()) {
-                Some(t) => return Some(t),
-                None => continue,
+        if self.dispatch.is_none() {
+            self.dispatch = Some(Rc::new(self.build_dispatch()));
+        }
+        let dispatch = self.dispatch.as_ref().unwrap();
+
+        loop {
+            // Matchers are still tried in the order they were registered;
+            // the index only skips those that can't start here
+            let found = match self.tokenizer.peek().cloned() {
+                Some(c) if (c as usize) < DISPATCH_SIZE => {
+                    let matchers = &self.matchers;
+                    first_match(&mut self.tokenizer, dispatch[c as usize].iter().map(|&i| &matchers[i]))
+                },
+                Some(c) => first_match(&mut self.tokenizer, self.matchers.iter().filter(|m| m.can_start(c))),
+                None    => first_match(&mut self.tokenizer, self.matchers.iter()),
+            };
+
+            if found.is_some() {
+                return found
+            }
+
+            if self.tokenizer.end() {
+                return None
             }
+
+            // Nothing matched, skip the offending character
+            let position = self.tokenizer.pos;
+            let c        = self.tokenizer.next().unwrap();
+            let message  = format!("unexpected character `{}`", c);
+
+            self.tokenizer.report(Diagnostic::new(Code::UnexpectedCharacter, message, position, self.tokenizer.pos));
         }
-        None
+    }
+
+    pub fn diagnostics(&self) -> &Vec<Diagnostic> {
+        self.tokenizer.diagnostics()
     }
     
     pub fn matchers(&self) -> &Vec<Box<Matcher>> {
@@ -139,6 +252,7 @@ impl Lexer {
     }
 
     pub fn matchers_mut(&mut self) -> &mut Vec<Box<Matcher>> {
+        self.dispatch = None;
         &mut self.matchers
     }
 }
@@ -147,7 +261,10 @@ impl Iterator for Lexer {
     type Item = Token;
 
     fn next(&mut self) -> Option<Token> {
-        let token = self.match_token().unwrap();
+        let token = match self.match_token() {
+            Some(t) => t,
+            None => return None,
+        };
         match *token.token_type() {
             TokenType::EOF => None,
             TokenType::Whitespace => {
@@ -1,3 +1,4 @@
+use diagnostic::{Code, Diagnostic};
 use lexer::Tokenizer;
 use lexer::token::{Token, TokenType};
 
@@ -8,19 +9,42 @@ macro_rules! token {
     ($tokenizer:expr, $token_type:expr, $accum:expr) => {{
         let tokenizer = $tokenizer as &$crate::lexer::Tokenizer;
         let token_type = $token_type as $crate::lexer::token::TokenType;
-        Some(Token::new(token_type, tokenizer.last_position(), $accum))
+        let mut token = Token::new(token_type, tokenizer.last_position(), $accum);
+        *token.end_mut() = tokenizer.pos;
+        Some(token)
+    }};
+}
+
+/// Reports an error spanning from the start of the
+/// current token up to the tokenizer's position.
+macro_rules! report {
+    ($tokenizer:expr, $code:expr, $($arg:tt)+) => {{
+        let tokenizer: &mut $crate::lexer::Tokenizer = $tokenizer;
+        let diagnostic = Diagnostic::new($code, format!($($arg)+), tokenizer.last_position(), tokenizer.pos);
+        tokenizer.report(diagnostic);
     }};
 }
 
 /// Matcher.
 pub trait Matcher {
     fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token>;
+
+    /// Whether a match can start with `c`. The lexer only tries a matcher
+    /// at positions it can start at, so this must hold for every `c` a
+    /// match could begin with; the default tries the matcher everywhere.
+    fn can_start(&self, _c: char) -> bool {
+        true
+    }
 }
 
 /// A matcher that only matches white-space.
 pub struct WhitespaceMatcher {}
 
 impl Matcher for WhitespaceMatcher {
+    fn can_start(&self, c: char) -> bool {
+        c.is_whitespace()
+    }
+
     fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token> {
         let mut found = false;
         while !tokenizer.end() && tokenizer.peek().unwrap().is_whitespace() {
@@ -39,6 +63,10 @@ impl Matcher for WhitespaceMatcher {
 pub struct IntLiteralMatcher {}
 
 impl Matcher for IntLiteralMatcher {
+    fn can_start(&self, c: char) -> bool {
+        c.is_digit(10)
+    }
+
     fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token> {
         let mut accum = String::new();
         let base = match tokenizer.peek().unwrap() {
@@ -66,9 +94,17 @@ impl Matcher for IntLiteralMatcher {
             // Produce token as base-10 string
             let literal: String = match u64::from_str_radix(accum.as_str(), base) {
                 Ok(result) => result.to_string(),
-                Err(error) => panic!("Unable to parse integer literal: {}", error)
+                Err(error) => {
+                    report!(tokenizer, Code::IntegerTooLarge, "unable to parse integer literal: {}", error);
+                    "0".to_string()
+                }
             };
             token!(tokenizer, IntLiteral, literal)
+        } else if base != 10 {
+            // The prefix alone; lex it as a zero so the error is reported once
+            let (name, prefix) = if base == 16 { ("hex", "0x") } else { ("binary", "0b") };
+            report!(tokenizer, Code::ExpectedDigits, "expected {} digits after `{}`", name, prefix);
+            token!(tokenizer, IntLiteral, "0".to_string())
         } else {
             None
         }
@@ -79,6 +115,10 @@ impl Matcher for IntLiteralMatcher {
 pub struct FloatLiteralMatcher {}
 
 impl Matcher for FloatLiteralMatcher {
+    fn can_start(&self, c: char) -> bool {
+        c.is_digit(10) || c == '.'
+    }
+
     fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token> {
         let mut accum = String::new();
         let curr = tokenizer.next().unwrap();
@@ -93,7 +133,9 @@ impl Matcher for FloatLiteralMatcher {
             let current = *tokenizer.peek().unwrap();
             if !current.is_whitespace() && current.is_digit(10) || current == '.' {
                 if current == '.' && accum.contains('.') {
-                    panic!("Unexpected decimal point")
+                    tokenizer.next();
+                    report!(tokenizer, Code::UnexpectedDecimalPoint, "unexpected decimal point");
+                    break
                 }
 
                 accum.push(tokenizer.next().unwrap());
@@ -104,11 +146,14 @@ impl Matcher for FloatLiteralMatcher {
         if accum.chars().last() == Some('.') {
             accum.push('0');
         }
-        if accum.contains('.') {
-            token!(tokenizer, FloatLiteral, accum)
-        } else {
-            None
+        if !accum.contains('.') {
+            return None
         }
+        if !accum.parse::<f32>().map_or(false, |f| f.is_finite()) {
+            report!(tokenizer, Code::FloatTooLarge, "float literal `{}` does not fit in 32 bits", accum);
+            accum = "0.0".to_string();
+        }
+        token!(tokenizer, FloatLiteral, accum)
     }
 }
 
@@ -116,6 +161,10 @@ impl Matcher for FloatLiteralMatcher {
 pub struct StringLiteralMatcher {}
 
 impl Matcher for StringLiteralMatcher {
+    fn can_start(&self, c: char) -> bool {
+        c == '"' || c == '\'' || c == 'r'
+    }
+
     fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token> {
         let mut raw_marker = false;
         let delimeter  = match tokenizer.peek().unwrap() {
@@ -149,7 +198,11 @@ impl Matcher for StringLiteralMatcher {
                             'n' => '\n',
                             'r' => '\r',
                             't' => '\t',
-                            s => panic!("Invalid character escape: {}", s),
+                            s => {
+                                report!(tokenizer, Code::InvalidEscape, "invalid character escape: \\{}", s);
+                                found_escape = false;
+                                continue
+                            },
                         }
                     );
                     found_escape = false
@@ -165,17 +218,21 @@ impl Matcher for StringLiteralMatcher {
                 }
             }
         }
-        tokenizer.advance(1); // Skips the closing delimeter
+        if tokenizer.end() {
+            let kind = if delimeter == Some('"') { "string" } else { "char" };
+            report!(tokenizer, Code::UnterminatedLiteral, "unterminated {} literal", kind);
+        } else {
+            tokenizer.advance(1); // Skips the closing delimeter
+        }
         match delimeter.unwrap() {
             '"'  => {
                 token!(tokenizer, StringLiteral, string)
             },
             _ => {
-                if string.len() == 1 {
-                    token!(tokenizer, CharLiteral, string)
-                } else {
-                    panic!("Invalid char literal")
+                if string.chars().count() != 1 {
+                    report!(tokenizer, Code::CharLength, "char literal must contain exactly one character");
                 }
+                token!(tokenizer, CharLiteral, string)
             },
         }
     }
@@ -198,15 +255,15 @@ impl ConstantMatcher {
 }
 
 impl Matcher for ConstantMatcher {
+    fn can_start(&self, c: char) -> bool {
+        self.constants.iter().any(|constant| constant.starts_with(c))
+    }
+
     fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token> {
-        for constant in self.constants.clone() {
-            let dat = tokenizer.clone().take(constant.len());
-            if dat.size_hint().1.unwrap() != constant.len() {
-                return None;
-            }
-            if dat.collect::<String>() == constant {
-                tokenizer.advance(constant.len());
-                return token!(tokenizer, self.token_type.clone(), constant)
+        for constant in &self.constants {
+            if tokenizer.starts_with(constant) {
+                tokenizer.advance(constant.chars().count());
+                return token!(tokenizer, self.token_type.clone(), constant.clone())
             }
         }
         None
@@ -217,6 +274,10 @@ impl Matcher for ConstantMatcher {
 pub struct IdentifierMatcher {}
 
 impl Matcher for IdentifierMatcher {
+    fn can_start(&self, c: char) -> bool {
+        c.is_alphabetic() || c == '_'
+    }
+
     fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token> {
         let mut identifier = String::new();
         let curr = tokenizer.next().unwrap();
@@ -7,4 +7,5 @@ pub mod block_tree;
 pub use self::lexer::Lexer;
 pub use self::token::{Token, TokenType};
 pub use self::tokenizer::Tokenizer;
-pub use self::lexer::{grab_smaragdine_lexer, lex_branch, flatten_branch};
\ No newline at end of file
+pub use self::lexer::{grab_smaragdine_lexer, lex_branch, flatten_branch};
+pub use self::lexer::{SYMBOLS, OPERATORS, KEYWORDS, BOOLEANS};
\ No newline at end of file
@@ -41,6 +41,15 @@ impl TokenPosition {
             col: col,
         }
     }
+
+    /// Translates a position relative to `origin` into an absolute one
+    pub fn offset(&self, origin: &TokenPosition) -> TokenPosition {
+        if self.line == 1 {
+            TokenPosition::new(origin.line, origin.col + self.col)
+        } else {
+            TokenPosition::new(origin.line + self.line - 1, self.col)
+        }
+    }
 }
 
 impl fmt::Display for TokenPosition {
@@ -54,6 +63,7 @@ impl fmt::Display for TokenPosition {
 pub struct Token {
     token_type: TokenType,
     position: TokenPosition,
+    end: TokenPosition,
     content: String,
 }
 
@@ -72,6 +82,7 @@ impl Token {
         Token {
             token_type: token_type,
             position: position,
+            end: position,
             content: content,
         }
     }
@@ -85,6 +96,11 @@ impl Token {
         &self.position
     }
 
+    /// Position just past the last character of the token
+    pub fn end(&self) -> &TokenPosition {
+        &self.end
+    }
+
     pub fn content(&self) -> &String {
         &self.content
     }
@@ -97,6 +113,10 @@ impl Token {
     pub fn position_mut(&mut self) -> &mut TokenPosition {
         &mut self.position
     }
+
+    pub fn end_mut(&mut self) -> &mut TokenPosition {
+        &mut self.end
+    }
 }
 
 impl<'a> PartialEq for Token {
@@ -1,3 +1,4 @@
+use diagnostic::Diagnostic;
 use lexer::matcher::Matcher;
 use lexer::token::{Token, TokenType, TokenPosition};
 
@@ -5,19 +6,26 @@ use lexer::token::{Token, TokenType, TokenPosition};
 pub struct Snapshot {
     pub pos: TokenPosition,
     index: usize,
+    diagnostics: usize,
 }
 
 impl Snapshot {
-    pub fn new(index: usize, pos: TokenPosition) -> Snapshot {
+    pub fn new(index: usize, pos: TokenPosition, diagnostics: usize) -> Snapshot {
         Snapshot {
             index: index,
             pos: pos,
+            diagnostics: diagnostics,
         }
     }
 
     pub fn index(&self) -> usize {
         self.index
     }
+
+    /// Number of diagnostics reported when the snapshot was taken
+    pub fn diagnostics(&self) -> usize {
+        self.diagnostics
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -26,6 +34,7 @@ pub struct Tokenizer {
     index: usize,
     items: Vec<char>,
     snapshots: Vec<Snapshot>,
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl Iterator for Tokenizer {
@@ -43,6 +52,7 @@ impl Tokenizer {
             pos: TokenPosition::default(),
             items: items.collect(),
             snapshots: Vec::new(),
+            diagnostics: Vec::new(),
         }
     }
 
@@ -65,6 +75,12 @@ impl Tokenizer {
         Some(&self.items[self.index + n])
     }
 
+    /// Whether the items from the current position on start with `s`
+    pub fn starts_with(&self, s: &str) -> bool {
+        let mut rest = self.items[self.index..].iter();
+        s.chars().all(|c| rest.next() == Some(&c))
+    }
+
     pub fn read(&mut self) -> Option<&char> {
         if self.end() {
             return None
@@ -87,7 +103,7 @@ impl Tokenizer {
     }
 
     pub fn take_snapshot(&mut self) {
-        self.snapshots.push(Snapshot::new(self.index, self.pos));
+        self.snapshots.push(Snapshot::new(self.index, self.pos, self.diagnostics.len()));
     }
 
     pub fn peek_snapshot(&self) -> Option<&Snapshot> {
@@ -98,6 +114,7 @@ impl Tokenizer {
         let snapshot = self.snapshots.pop().unwrap();
         self.index = snapshot.index();
         self.pos = snapshot.pos;
+        self.diagnostics.truncate(snapshot.diagnostics());
     }
 
     pub fn commit_snapshot(&mut self) {
@@ -108,6 +125,12 @@ impl Tokenizer {
         self.peek_snapshot().unwrap().pos
     }
 
+    /// Records an error. Diagnostics reported by a matcher
+    /// are discarded if its snapshot is rolled back.
+    pub fn report(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
     pub fn try_match_token(&mut self, matcher: &Matcher) -> Option<Token> {
         if self.end() {
             return Some(Token::new(TokenType::EOF,
@@ -133,4 +156,8 @@ impl Tokenizer {
     pub fn index(&self) -> &usize {
         &self.index
     }
+
+    pub fn diagnostics(&self) -> &Vec<Diagnostic> {
+        &self.diagnostics
+    }
 }
\ No newline at end of file
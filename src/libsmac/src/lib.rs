@@ -1,14 +1,37 @@
 #![feature(plugin)]
 #![plugin(indoc)]
 
+#[cfg(feature = "wasm")]
+extern crate js_sys;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+
+pub mod capi;
+pub mod diagnostic;
+pub mod formatter;
+pub mod grammar;
+pub mod highlight;
 pub mod lexer;
+pub mod lint;
 pub mod parser;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 #[cfg(test)]
 mod tests {
-    use lexer::{Lexer, TokenType};
+    use capi;
+    use diagnostic::{self, Code, Diagnostic, Severity};
+    use formatter;
+    use grammar;
+    use highlight::{self, Group};
+    use lint;
+    use lexer::{Lexer, Token, TokenType, Tokenizer};
     use lexer::grab_smaragdine_lexer;
+    use lexer::matcher::Matcher;
+    use parser::parse_source;
+    use std::ffi::{CStr, CString};
     use std::iter::Iterator;
+    use std::ptr;
 
     macro_rules! match_seq {
         (list src: $src:expr, $ttype:path => [ $($tvalue:expr),+, ]) => {{
@@ -102,6 +125,26 @@ mod tests {
         )
     }
 
+    #[test]
+    fn lex_integer_prefix_without_digits() {
+        let (tokens, diagnostics) = Lexer::tokenize("0x 0b + 0xg");
+        let types: Vec<_> = tokens.iter().map(|t| t.token_type().clone()).collect();
+        let lines: Vec<_> = diagnostics.iter().map(|d| format!("{}", d)).collect();
+
+        assert_eq!(types, vec![
+            TokenType::IntLiteral,
+            TokenType::IntLiteral,
+            TokenType::Operator,
+            TokenType::IntLiteral,
+            TokenType::Identifier,
+        ]);
+        assert_eq!(lines, vec![
+            "1:1: error[E0012]: expected hex digits after `0x`",
+            "1:4: error[E0012]: expected binary digits after `0b`",
+            "1:9: error[E0012]: expected hex digits after `0x`",
+        ]);
+    }
+
     #[test]
     fn lex_float() {
         match_seq!(
@@ -122,4 +165,338 @@ mod tests {
             ]
         )
     }
+
+    #[test]
+    fn lex_token_spans() {
+        let tokens: Vec<_> = grab_smaragdine_lexer(&mut "let x = 0xFF\n  \"hi\"".chars()).collect();
+        let spans: Vec<_> = tokens.iter()
+                                  .map(|t| ((t.position().line, t.position().col),
+                                            (t.end().line, t.end().col)))
+                                  .collect();
+
+        assert_eq!(spans, vec![
+            ((1, 0), (1, 3)),
+            ((1, 4), (1, 5)),
+            ((1, 6), (1, 7)),
+            ((1, 8), (1, 12)),
+            ((2, 2), (2, 6)),
+        ]);
+    }
+
+    #[test]
+    fn lex_tokenize_collects_everything() {
+        let (tokens, diagnostics) = Lexer::tokenize("1 $ 2 @ \"open");
+
+        let contents: Vec<_> = tokens.iter().map(|t| t.content().as_str()).collect();
+        let lines: Vec<_>    = diagnostics.iter().map(|d| format!("{}", d)).collect();
+
+        assert_eq!(contents, vec!["1", "2", "open"]);
+        assert_eq!(lines, vec![
+            "1:3: error[E0001]: unexpected character `$`",
+            "1:7: error[E0001]: unexpected character `@`",
+            "1:9: error[E0005]: unterminated string literal",
+        ]);
+    }
+
+    /// Tries the matcher it wraps everywhere, bypassing the dispatch index
+    struct Everywhere(Box<Matcher>);
+
+    impl Matcher for Everywhere {
+        fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token> {
+            self.0.try_match(tokenizer)
+        }
+    }
+
+    #[test]
+    fn lex_dispatch_matches_trying_everything() {
+        let src = "let x_1: int = 0xFF + 0b10 * 1.5 / .5\nif a? != b! -> [{\"s\\n\": 'c'}]\nr\"raw\" éa @ 1.2.3 $ true else <<= >= ~x % y || &&";
+
+        let mut everywhere = grab_smaragdine_lexer(&mut "".chars());
+        let matchers: Vec<_> = everywhere.matchers_mut().drain(..).collect();
+        let mut everywhere = Lexer::new(Tokenizer::new(&mut src.chars()));
+        everywhere.matchers_mut().extend(matchers.into_iter().map(|m| Box::new(Everywhere(m)) as Box<Matcher>));
+
+        let (tokens, diagnostics) = Lexer::tokenize(src);
+        let expected: Vec<_>      = everywhere.by_ref().collect();
+        let spans   = |tokens: &[Token]| tokens.iter().map(|t| format!("{} {}", t, t.end())).collect::<Vec<_>>();
+        let lines   = |diagnostics: &[Diagnostic]| diagnostics.iter().map(|d| format!("{}", d)).collect::<Vec<_>>();
+
+        assert_eq!(spans(&tokens), spans(&expected));
+        assert_eq!(lines(&diagnostics), lines(everywhere.diagnostics()));
+    }
+
+    #[test]
+    fn lex_recovers_from_errors() {
+        let mut lexer = grab_smaragdine_lexer(&mut "1.2.3 $ '' \"\\q\" 'é' \"open".chars());
+        let tokens: Vec<_> = lexer.by_ref().collect();
+        let types: Vec<_> = tokens.iter().map(|t| t.token_type().clone()).collect();
+        let lines: Vec<_> = lexer.diagnostics().iter().map(|d| format!("{}", d)).collect();
+
+        assert_eq!(types, vec![
+            TokenType::FloatLiteral,
+            TokenType::IntLiteral,
+            TokenType::CharLiteral,
+            TokenType::StringLiteral,
+            TokenType::CharLiteral,
+            TokenType::StringLiteral,
+        ]);
+        assert_eq!(lines, vec![
+            "1:1: error[E0003]: unexpected decimal point",
+            "1:7: error[E0001]: unexpected character `$`",
+            "1:9: error[E0006]: char literal must contain exactly one character",
+            "1:12: error[E0004]: invalid character escape: \\q",
+            "1:21: error[E0005]: unterminated string literal",
+        ]);
+    }
+
+    #[test]
+    fn parse_collections() {
+        let (nodes, diagnostics) = parse_source("[1, [2.5], {'a': true,}][0] {} []");
+        let nodes: Vec<_> = nodes.iter().map(|n| format!("{}", n)).collect();
+
+        assert!(diagnostics.is_empty());
+        assert_eq!(nodes, vec![
+            "(index (list (int 1) (list (float 2.5)) (map ((char 'a') (bool true)))) (int 0))",
+            "(map)",
+            "(list)",
+        ]);
+    }
+
+    #[test]
+    fn parse_recovers_in_collections() {
+        let (nodes, diagnostics) = parse_source("[1 2] {1: 2, 3} [(4] 5 [6");
+        let nodes: Vec<_> = nodes.iter().map(|n| format!("{}", n)).collect();
+        let lines: Vec<_> = diagnostics.iter().map(|d| format!("{}", d)).collect();
+
+        assert_eq!(nodes, vec!["(list (int 1))", "(map ((int 1) (int 2)))", "(list)", "(int 5)", "(list (int 6))"]);
+        assert_eq!(lines, vec![
+            "1:4: error[E0008]: expected `,` or `]`, found IntLiteral `2`",
+            "1:15: error[E0008]: expected a `key: value` entry, found Symbol `}`",
+            "1:18: error[E0008]: expected an element, found Symbol `(`",
+            "1:24: error[E0009]: unclosed `[`",
+        ]);
+    }
+
+    #[test]
+    fn parse_nesting_limit() {
+        let (nodes, diagnostics) = parse_source(&"[".repeat(100_000));
+
+        // Each of the 256 lists entered is reported unclosed, too
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(format!("{}", diagnostics.last().unwrap()), "1:257: error[E0010]: brackets nested more than 256 deep");
+
+        let source = format!("{}1{}", "[".repeat(300), "]".repeat(300));
+        let (nodes, diagnostics) = parse_source(&format!("{} 2", source));
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(format!("{}", nodes[1]), "(int 2)");
+        assert!(parse_source(&format!("1{}", "[0]".repeat(300))).1[0].code() == Some(Code::TooDeep));
+    }
+
+    #[test]
+    fn diagnostic_codes_explained() {
+        let linter   = lint::grab_smaragdine_linter();
+        // Warnings are checked like `smac lint` does, on the tokens alone
+        let raise    = |source: &str, severity| {
+            if severity == Severity::Warning {
+                let (tokens, mut diagnostics) = Lexer::tokenize(source);
+                diagnostics.append(&mut linter.lint(&tokens));
+                diagnostics
+            } else {
+                parse_source(source).1
+            }
+        };
+        let mut last = ('E', 0);
+
+        for &code in diagnostic::CODES {
+            let explanation = code.explanation();
+
+            // Numbered in order from 1, errors first and then warnings
+            let prefix = explanation.id.chars().next().unwrap();
+            assert!(prefix >= last.0, "{} comes after the warnings", explanation.id);
+            last = if prefix == last.0 { (prefix, last.1 + 1) } else { (prefix, 1) };
+            assert_eq!(explanation.id, format!("{}{:04}", last.0, last.1));
+            assert_eq!(Code::from_id(explanation.id), Some(code));
+
+            // Too long a source to show; parse_nesting_limit covers it
+            if code == Code::TooDeep {
+                continue
+            }
+
+            let raised: Vec<_> = raise(explanation.example, code.severity()).iter().filter_map(|d| d.code()).collect();
+
+            assert!(raised.contains(&code), "{} example raises {:?}", explanation.id, raised);
+            assert!(raise(explanation.fix, code.severity()).is_empty(), "{} fix has diagnostics", explanation.id);
+        }
+        assert_eq!(last.0, 'W');
+    }
+
+    #[test]
+    fn format_normalizes_layout() {
+        let source = "let  f(y)->int=x+y\n\n\n  let b!( val : bool ) =  -1\n  if letter\n      0xFF\nr\"a  b\"   'c'\n\n";
+        let formatted = formatter::format(source, &Default::default()).unwrap();
+
+        assert_eq!(formatted, "let f(y) -> int = x + y\n\n    let b!(val: bool) = -1\n    if letter\n        0xFF\nr\"a  b\" 'c'\n");
+        assert_eq!(formatter::format(&formatted, &Default::default()).unwrap(), formatted);
+        assert_eq!(formatter::format("xs[ 0 ] [1] { 'a' : -1 , 'b' : [ 2 ,3 ] }", &Default::default()).unwrap(),
+                   "xs[0] [1] {'a': -1, 'b': [2, 3]}\n");
+    }
+
+    #[test]
+    fn format_indent_width() {
+        let config = formatter::Config { indent_width: 2, ..Default::default() };
+
+        assert_eq!(formatter::format("1\n    2\n        3\n", &config).unwrap(), "1\n  2\n    3\n");
+    }
+
+    #[test]
+    fn format_keeps_sibling_blocks() {
+        // Two blocks in a row; indenting both by one level would merge them
+        let source    = "1\n    2\n  3\n";
+        let formatted = formatter::format(source, &Default::default()).unwrap();
+
+        assert_eq!(formatted, source);
+        assert_eq!(parse_source(&formatted).1.len(), parse_source(source).1.len());
+        assert_eq!(formatter::format("x=-1\na - -b\n", &Default::default()).unwrap(), "x = -1\na - -b\n");
+    }
+
+    #[test]
+    fn format_breaks_long_lines() {
+        let config = formatter::Config { max_line_length: 20, ..Default::default() };
+        let source = "xs = [1111, 2222, {'a': 3333, 'b': 4444}, 5555]\n  [1,\n  2]\n";
+
+        let formatted = formatter::format(source, &config).unwrap();
+
+        assert_eq!(formatted, "xs = [1111, 2222,\n{'a': 3333,\n'b': 4444}, 5555]\n    [1, 2]\n");
+        assert_eq!(formatter::format(&formatted, &config).unwrap(), formatted);
+        let nodes = |source: &str| parse_source(source).0.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        assert_eq!(nodes(&formatted), nodes(source));
+    }
+
+    #[test]
+    fn format_trailing_commas() {
+        let source = "[1, 2,] {'a': [3333, 4444],}\n";
+
+        assert_eq!(formatter::format(source, &Default::default()).unwrap(), "[1, 2] {'a': [3333, 4444]}\n");
+
+        let config    = formatter::Config { max_line_length: 20, trailing_comma: true, ..Default::default() };
+        let formatted = formatter::format(source, &config).unwrap();
+
+        assert_eq!(formatted, "[1, 2] {'a': [3333,\n4444,],}\n");
+        assert_eq!(formatter::format(&formatted, &config).unwrap(), formatted);
+    }
+
+    #[test]
+    fn format_rejects_errors() {
+        let diagnostics = formatter::format("1\n  \"open\n", &Default::default()).unwrap_err();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(format!("{}", diagnostics[0]), "2:3: error[E0005]: unterminated string literal");
+    }
+
+    #[test]
+    fn lint_default_rules() {
+        let src    = "let camelCase = 2\nlet b = camelCase * 60\nlet b = b == b\n[1 == 1, {0: b == true}]\n";
+        let tokens = grab_smaragdine_lexer(&mut src.chars()).collect::<Vec<_>>();
+        let lints  = lint::grab_smaragdine_linter().lint(&tokens);
+
+        let lines = lints.iter().map(|d| format!("{}", d)).collect::<Vec<_>>();
+        assert_eq!(lines, vec![
+            "1:5: warning[W0001]: `camelCase` should be snake_case [naming]",
+            "2:21: warning[W0004]: magic number `60`, consider binding it to a name [magic_numbers]",
+            "3:5: warning[W0003]: `b` shadows the binding on line 2 [shadowing]",
+            "3:11: warning[W0002]: `b` is compared with itself [suspicious_comparison]",
+            "4:4: warning[W0002]: `1` is compared with itself [suspicious_comparison]",
+            "4:16: warning[W0002]: comparison with `true` is redundant [suspicious_comparison]",
+        ]);
+    }
+
+    #[test]
+    fn lint_configured_rules() {
+        let src    = "let f(x_y, n: int) = (x_y == true) + 60\n";
+        let tokens = grab_smaragdine_lexer(&mut src.chars()).collect::<Vec<_>>();
+
+        let mut linter = lint::grab_smaragdine_linter();
+        linter.rules_mut().retain(|r| r.name() != "suspicious_comparison");
+        assert!(linter.lint(&tokens).iter().any(|d| d.message().contains("`60`")));
+
+        linter.rules_mut().retain(|r| r.name() != "magic_numbers");
+        linter.rules_mut().push(Box::new(lint::MagicNumbers { allowed: vec![60] }));
+        assert!(linter.lint(&tokens).is_empty());
+    }
+
+    #[test]
+    fn highlight_covers_source() {
+        let src    = "let s = \"hi\" $ (true)\n  1.5\n";
+        let pieces = highlight::highlight(src);
+
+        assert_eq!(pieces.iter().map(|p| p.1.as_str()).collect::<String>(), src);
+        assert_eq!(pieces.iter().filter_map(|p| p.0).collect::<Vec<_>>(), vec![
+            Group::Keyword, Group::Identifier, Group::Operator, Group::String,
+            Group::Punctuation, Group::Boolean, Group::Punctuation, Group::Number,
+        ]);
+    }
+
+    #[test]
+    fn grammar_lists_tokens() {
+        let rules   = grammar::rules();
+        let keyword = rules.iter().find(|r| r.name == "keyword").unwrap();
+
+        assert_eq!(keyword.alternatives, vec!["'let'", "'if'", "'else'"]);
+        assert!(grammar::ebnf(&rules).contains("\nkeyword            = 'let'\n                   | 'if'\n"));
+    }
+
+    #[test]
+    fn capi_compile() {
+        let source = CString::new("[1, 2] @\n3").unwrap();
+
+        unsafe {
+            let compilation = capi::smac_compile(source.as_ptr());
+            assert!(!compilation.is_null());
+
+            assert_eq!(capi::smac_node_count(compilation), 2);
+            assert_eq!(capi::smac_diagnostic_count(compilation), 1);
+            assert_eq!(capi::smac_diagnostic_severity(compilation, 0), 0);
+            assert_eq!(capi::smac_diagnostic_line(compilation, 0), 1);
+            assert_eq!(capi::smac_diagnostic_column(compilation, 0), 8);
+            assert_eq!(CStr::from_ptr(capi::smac_diagnostic_code(compilation, 0)).to_str(), Ok("E0001"));
+            assert!(!capi::smac_diagnostic_message(compilation, 0).is_null());
+
+            assert_eq!(capi::smac_diagnostic_severity(compilation, 1), -1);
+            assert!(capi::smac_diagnostic_message(compilation, 1).is_null());
+            assert!(capi::smac_diagnostic_code(compilation, 1).is_null());
+            assert_eq!(capi::smac_diagnostic_column(compilation, 1), 0);
+
+            capi::smac_compilation_free(compilation);
+
+            let invalid = CString::new(vec![b'1', 0xff]).unwrap();
+            assert!(capi::smac_compile(invalid.as_ptr()).is_null());
+            assert!(capi::smac_compile(ptr::null()).is_null());
+            assert_eq!(capi::smac_diagnostic_count(ptr::null()), 0);
+            capi::smac_compilation_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn capi_header_declares_every_function() {
+        // The names of `smac_*` functions declared or defined in `source`,
+        // found as the identifier before `(`
+        fn functions(source: &str, after: &str) -> Vec<String> {
+            let mut names: Vec<_> = source.split(after)
+                                          .skip(1)
+                                          .filter_map(|rest| rest.find('(').map(|end| &rest[..end]))
+                                          .filter_map(|declared| declared.split_whitespace().last())
+                                          .map(|name| name.trim_start_matches('*').to_string())
+                                          .filter(|name| name.starts_with("smac_"))
+                                          .collect();
+            names.sort();
+            names
+        }
+
+        let header  = include_str!("../include/smac.h");
+        let defined = functions(include_str!("capi.rs"), "extern \"C\" fn ");
+
+        assert!(defined.contains(&"smac_compile".to_string()));
+        assert_eq!(functions(header, "\n"), defined);
+    }
 }
@@ -0,0 +1,275 @@
+use diagnostic::{Code, Diagnostic};
+use lexer::{Token, TokenType};
+
+use std::collections::HashMap;
+
+/// A check over the token stream of a source file. There is no full
+/// syntax tree yet, so rules look at tokens and their neighbours.
+pub trait LintRule {
+    /// Name used to refer to the rule in output and configuration
+    fn name(&self) -> &'static str;
+
+    /// Code of the warnings the rule raises
+    fn code(&self) -> Code;
+
+    fn check(&self, tokens: &[Token]) -> Vec<Diagnostic>;
+}
+
+pub fn grab_smaragdine_linter() -> Linter {
+    let mut linter = Linter::new();
+
+    linter.rules_mut().push(Box::new(Naming {}));
+    linter.rules_mut().push(Box::new(SuspiciousComparison {}));
+    linter.rules_mut().push(Box::new(Shadowing {}));
+    linter.rules_mut().push(Box::new(MagicNumbers::default()));
+
+    linter
+}
+
+pub struct Linter {
+    rules: Vec<Box<LintRule>>,
+}
+
+impl Linter {
+    pub fn new() -> Linter {
+        Linter {
+            rules: Vec::new(),
+        }
+    }
+
+    /// Runs every rule, returning their warnings in source order
+    pub fn lint(&self, tokens: &[Token]) -> Vec<Diagnostic> {
+        let mut lints = Vec::new();
+
+        for rule in &self.rules {
+            lints.append(&mut rule.check(tokens));
+        }
+        lints.sort_by_key(|d| (d.position().line, d.position().col));
+
+        lints
+    }
+
+    pub fn rules(&self) -> &Vec<Box<LintRule>> {
+        &self.rules
+    }
+
+    pub fn rules_mut(&mut self) -> &mut Vec<Box<LintRule>> {
+        &mut self.rules
+    }
+}
+
+/// A warning about `token`, tagged with the rule that raised it
+fn warn(rule: &LintRule, token: &Token, message: String) -> Diagnostic {
+    Diagnostic::warning(rule.code(), format!("{} [{}]", message, rule.name()), *token.position(), *token.end())
+}
+
+fn is(token: Option<&Token>, token_type: TokenType, content: &[&str]) -> bool {
+    match token {
+        Some(t) => *t.token_type() == token_type && content.contains(&t.content().as_str()),
+        None    => false,
+    }
+}
+
+/// Names introduced by `let`: the bound name, and any parameters
+/// in the parentheses following it
+fn bindings(tokens: &[Token]) -> Vec<(&Token, bool)> {
+    let mut names = Vec::new();
+
+    for (i, t) in tokens.iter().enumerate() {
+        if !is(Some(t), TokenType::Keyword, &["let"]) {
+            continue
+        }
+
+        match tokens.get(i + 1) {
+            Some(name) if *name.token_type() == TokenType::Identifier => names.push((name, false)),
+            _                                                          => continue,
+        }
+
+        if !is(tokens.get(i + 2), TokenType::Symbol, &["("]) {
+            continue
+        }
+
+        for j in i + 3..tokens.len() {
+            let param = &tokens[j];
+
+            if is(Some(param), TokenType::Symbol, &[")"]) {
+                break
+            }
+            // Identifiers after `:` are types
+            if *param.token_type() == TokenType::Identifier && !is(tokens.get(j - 1), TokenType::Operator, &[":"]) {
+                names.push((param, true))
+            }
+        }
+    }
+
+    names
+}
+
+/// Bound names should be snake_case
+pub struct Naming {}
+
+impl LintRule for Naming {
+    fn name(&self) -> &'static str {
+        "naming"
+    }
+
+    fn code(&self) -> Code {
+        Code::NonSnakeCase
+    }
+
+    fn check(&self, tokens: &[Token]) -> Vec<Diagnostic> {
+        bindings(tokens).into_iter()
+                        .map(|(t, _)| t)
+                        .filter(|t| {
+                            !t.content()
+                              .trim_end_matches(|c| c == '?' || c == '!')
+                              .chars()
+                              .all(|c| c.is_lowercase() || c.is_digit(10) || c == '_')
+                        })
+                        .map(|t| warn(self, t, format!("`{}` should be snake_case", t.content())))
+                        .collect()
+    }
+}
+
+/// Comparisons of a value with itself, or with a boolean literal
+pub struct SuspiciousComparison {}
+
+impl LintRule for SuspiciousComparison {
+    fn name(&self) -> &'static str {
+        "suspicious_comparison"
+    }
+
+    fn code(&self) -> Code {
+        Code::SuspiciousComparison
+    }
+
+    fn check(&self, tokens: &[Token]) -> Vec<Diagnostic> {
+        let mut lints = Vec::new();
+
+        for i in 1..tokens.len().saturating_sub(1) {
+            let op = &tokens[i];
+
+            if !is(Some(op), TokenType::Operator, &["==", "<", ">", "<=", ">="]) {
+                continue
+            }
+
+            let (lhs, rhs) = (&tokens[i - 1], &tokens[i + 1]);
+
+            // Only operands that stand alone; a tighter operator
+            // on either side would make them part of a larger term
+            let bounded = |t: Option<&Token>, operand: &Token| match t {
+                Some(t) if t.position().line == operand.position().line => {
+                    is(Some(t), TokenType::Operator, &["=", ":", "&&", "||", "&", "^", "|"])
+                        || is(Some(t), TokenType::Symbol, &["(", ")", "[", "]", "{", "}", ","])
+                        || *t.token_type() == TokenType::Keyword
+                },
+                _ => true,
+            };
+
+            if !bounded(if i >= 2 { tokens.get(i - 2) } else { None }, lhs) || !bounded(tokens.get(i + 2), rhs) {
+                continue
+            }
+
+            if lhs == rhs && lhs.content() == rhs.content() {
+                lints.push(warn(self, op, format!("`{}` is compared with itself", lhs.content())))
+            } else if op.content() == "==" {
+                for t in &[lhs, rhs] {
+                    if *t.token_type() == TokenType::BooleanLiteral {
+                        lints.push(warn(self, op, format!("comparison with `{}` is redundant", t.content())))
+                    }
+                }
+            }
+        }
+
+        lints
+    }
+}
+
+/// Bindings that reuse a name bound earlier in the file. Without a
+/// resolver there are no scopes to go by, so every earlier `let` counts.
+pub struct Shadowing {}
+
+impl LintRule for Shadowing {
+    fn name(&self) -> &'static str {
+        "shadowing"
+    }
+
+    fn code(&self) -> Code {
+        Code::Shadowing
+    }
+
+    fn check(&self, tokens: &[Token]) -> Vec<Diagnostic> {
+        let mut lints = Vec::new();
+        let mut bound = HashMap::new();
+
+        for (t, parameter) in bindings(tokens) {
+            if parameter {
+                continue
+            }
+
+            match bound.get(t.content()) {
+                Some(line) => {
+                    let message = format!("`{}` shadows the binding on line {}", t.content(), line);
+                    lints.push(warn(self, t, message))
+                },
+                None => (),
+            }
+            bound.entry(t.content().clone()).or_insert(t.position().line);
+        }
+
+        lints
+    }
+}
+
+/// Numeric literals used outside of a `let` binding of their own
+pub struct MagicNumbers {
+    /// Numbers that are fine to use anywhere
+    pub allowed: Vec<i64>,
+}
+
+impl Default for MagicNumbers {
+    fn default() -> Self {
+        MagicNumbers {
+            allowed: vec![0, 1],
+        }
+    }
+}
+
+impl LintRule for MagicNumbers {
+    fn name(&self) -> &'static str {
+        "magic_numbers"
+    }
+
+    fn code(&self) -> Code {
+        Code::MagicNumber
+    }
+
+    fn check(&self, tokens: &[Token]) -> Vec<Diagnostic> {
+        let mut lints = Vec::new();
+
+        for (i, t) in tokens.iter().enumerate() {
+            match *t.token_type() {
+                TokenType::IntLiteral | TokenType::FloatLiteral => (),
+                _                                               => continue,
+            }
+
+            let value = t.content().parse::<f64>().unwrap_or(0.0);
+            if self.allowed.iter().any(|&n| n as f64 == value) {
+                continue
+            }
+
+            // `let name = <literal>` and `let name = -<literal>` name the number
+            let start = if i > 0 && is(tokens.get(i - 1), TokenType::Operator, &["-"]) { i - 1 } else { i };
+            let named = start >= 3
+                && is(tokens.get(start - 1), TokenType::Operator, &["="])
+                && *tokens[start - 2].token_type() == TokenType::Identifier
+                && is(tokens.get(start - 3), TokenType::Keyword, &["let"]);
+
+            if !named {
+                lints.push(warn(self, t, format!("magic number `{}`, consider binding it to a name", t.content())))
+            }
+        }
+
+        lints
+    }
+}
@@ -1,7 +1,8 @@
-use parser::nodizer::Nodizer;
+use diagnostic::{Code, Diagnostic};
+use parser::nodizer::{Nodizer, MAX_DEPTH};
 use parser::node::{Node, NodeType};
 
-use parser::token::TokenType;
+use parser::token::{Token, TokenType};
 
 pub trait Matcher {
     fn try_match(&self, nodizer: &mut Nodizer) -> Option<Node>;
@@ -20,12 +21,20 @@ impl Matcher for LiteralMatcher {
             },
             &TokenType::CharLiteral => {
                 nodizer.next();
-                let value = token.content().chars().nth(0).unwrap();
+                // Malformed char literals are reported by the lexer
+                let value = token.content().chars().nth(0).unwrap_or_default();
                 Some(Node::new(NodeType::CharLiteral(value)))
             },
             &TokenType::IntLiteral => {
                 nodizer.next();
-                let value = token.content().parse::<i32>().unwrap();
+                let value = match token.content().parse::<i32>() {
+                    Ok(value) => value,
+                    Err(_)    => {
+                        let message = format!("integer literal `{}` does not fit in 32 bits", token.content());
+                        nodizer.report(Diagnostic::new(Code::IntegerTooLarge, message, *token.position(), *token.end()));
+                        0
+                    },
+                };
                 Some(Node::new(NodeType::IntLiteral(value)))
             },
             &TokenType::FloatLiteral => {
@@ -41,4 +50,215 @@ impl Matcher for LiteralMatcher {
             _ => None,
         }
     }
+}
+
+/// Matches a literal, list or map, followed by any number of `[index]`es.
+/// An index must touch what it indexes, so `xs [0]` is two expressions.
+pub struct ExpressionMatcher {}
+
+impl Matcher for ExpressionMatcher {
+    fn try_match(&self, nodizer: &mut Nodizer) -> Option<Node> {
+        let mut node = if at(nodizer, "[") {
+            nested(nodizer, "]", list).unwrap_or_else(|| Node::new(NodeType::ListLiteral(Vec::new())))
+        } else if at(nodizer, "{") {
+            nested(nodizer, "}", map).unwrap_or_else(|| Node::new(NodeType::MapLiteral(Vec::new())))
+        } else {
+            nodizer.try_match_node(&LiteralMatcher {})?
+        };
+
+        // Each index nests the node one level deeper
+        let mut indexes = 0;
+
+        while at(nodizer, "[") && touching(nodizer) {
+            if !nodizer.enter() {
+                too_deep(nodizer, "]");
+                break
+            }
+            indexes += 1;
+
+            match index(nodizer, node) {
+                Ok(indexed) => node = indexed,
+                Err(target) => {
+                    node = target;
+                    break
+                },
+            }
+        }
+
+        for _ in 0..indexes {
+            nodizer.leave();
+        }
+
+        Some(node)
+    }
+}
+
+/// Runs `f` on the brackets at the nodizer's position, one level deeper,
+/// unless they would nest too deeply
+fn nested<F: FnOnce(&mut Nodizer) -> Node>(nodizer: &mut Nodizer, end: &str, f: F) -> Option<Node> {
+    if !nodizer.enter() {
+        too_deep(nodizer, end);
+        return None
+    }
+
+    let node = f(nodizer);
+    nodizer.leave();
+    Some(node)
+}
+
+/// Reports and skips brackets that would nest deeper than `MAX_DEPTH`
+fn too_deep(nodizer: &mut Nodizer, end: &str) {
+    let open    = nodizer.next().unwrap();
+    let message = format!("brackets nested more than {} deep", MAX_DEPTH);
+
+    nodizer.report(Diagnostic::new(Code::TooDeep, message, *open.position(), *open.end()));
+    recover(nodizer, end);
+}
+
+/// `[index]` applied to `target`, or the target back if it is malformed
+fn index(nodizer: &mut Nodizer, target: Node) -> Result<Node, Node> {
+    let open  = nodizer.next().unwrap();
+    let index = match expression(nodizer) {
+        Some(index) => index,
+        None        => {
+            expected(nodizer, &open, "an index");
+            recover(nodizer, "]");
+            return Err(target)
+        },
+    };
+
+    if !close(nodizer, &open, "]") {
+        return Err(target)
+    }
+
+    Ok(Node::new(NodeType::Index(Box::new(target), Box::new(index))))
+}
+
+fn expression(nodizer: &mut Nodizer) -> Option<Node> {
+    nodizer.try_match_node(&ExpressionMatcher {})
+}
+
+/// `[a, b, ...]`, with an optional trailing comma
+fn list(nodizer: &mut Nodizer) -> Node {
+    let items = elements(nodizer, "]", "an element", expression);
+
+    Node::new(NodeType::ListLiteral(items))
+}
+
+/// `{key: value, ...}`, with an optional trailing comma
+fn map(nodizer: &mut Nodizer) -> Node {
+    let entries = elements(nodizer, "}", "a `key: value` entry", |nodizer| {
+        let key = expression(nodizer)?;
+
+        if !at(nodizer, ":") {
+            return None
+        }
+        nodizer.next();
+
+        Some((key, expression(nodizer)?))
+    });
+
+    Node::new(NodeType::MapLiteral(entries))
+}
+
+/// Parses comma-separated elements up to `end`, starting at the opening
+/// bracket. On an error the rest of the collection is skipped.
+fn elements<T, F>(nodizer: &mut Nodizer, end: &str, what: &str, element: F) -> Vec<T>
+    where F: Fn(&mut Nodizer) -> Option<T> {
+    let open      = nodizer.next().unwrap();
+    let mut items = Vec::new();
+
+    loop {
+        if at(nodizer, end) {
+            nodizer.next();
+            break
+        }
+
+        match element(nodizer) {
+            Some(item) => items.push(item),
+            None       => {
+                expected(nodizer, &open, what);
+                recover(nodizer, end);
+                break
+            },
+        }
+
+        if at(nodizer, ",") {
+            nodizer.next();
+        } else if !at(nodizer, end) {
+            expected(nodizer, &open, &format!("`,` or `{}`", end));
+            recover(nodizer, end);
+            break
+        }
+    }
+
+    items
+}
+
+/// Whether the next token is the symbol or operator `content`
+fn at(nodizer: &Nodizer, content: &str) -> bool {
+    nodizer.peek().map_or(false, |t| match *t.token_type() {
+        TokenType::Symbol | TokenType::Operator => t.content() == content,
+        _                                       => false,
+    })
+}
+
+/// Whether the next token starts where the last one read ended
+fn touching(nodizer: &Nodizer) -> bool {
+    match (nodizer.last(), nodizer.peek()) {
+        (Some(last), Some(next)) => last.end().line == next.position().line && last.end().col == next.position().col,
+        _                        => false,
+    }
+}
+
+/// Consumes `end`, the bracket closing `open`. If something else comes
+/// first, reports it and skips past `end`.
+fn close(nodizer: &mut Nodizer, open: &Token, end: &str) -> bool {
+    if at(nodizer, end) {
+        nodizer.next();
+        return true
+    }
+
+    expected(nodizer, open, &format!("`{}`", end));
+    recover(nodizer, end);
+    false
+}
+
+/// Reports that `what` was expected inside the brackets opened by `open`
+fn expected(nodizer: &mut Nodizer, open: &Token, what: &str) {
+    let diagnostic = match nodizer.peek() {
+        Some(t) => {
+            let found = match *t.token_type() {
+                TokenType::Block(_) => "indented block".to_string(),
+                ref kind            => format!("{:?} `{}`", kind, t.content()),
+            };
+            Diagnostic::new(Code::Expected, format!("expected {}, found {}", what, found), *t.position(), *t.end())
+        },
+        None => Diagnostic::new(Code::Unclosed, format!("unclosed `{}`", open.content()), *open.position(), *open.end()),
+    };
+
+    nodizer.report(diagnostic);
+}
+
+/// Skips up to and including `end`, passing over nested brackets. An
+/// `end` that doesn't close the innermost of them still ends the skip.
+fn recover(nodizer: &mut Nodizer, end: &str) {
+    let mut nested = Vec::new();
+
+    while !nodizer.end() {
+        if at(nodizer, end) && nested.last() != Some(&end) {
+            nodizer.next();
+            return
+        }
+
+        for &(open, close) in &[("(", ")"), ("[", "]"), ("{", "}")] {
+            if at(nodizer, open) {
+                nested.push(close);
+            } else if at(nodizer, close) && nested.last() == Some(&close) {
+                nested.pop();
+            }
+        }
+
+        nodizer.next();
+    }
 }
\ No newline at end of file
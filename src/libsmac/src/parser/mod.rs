@@ -6,4 +6,4 @@ pub mod parser;
 pub use super::lexer;
 pub use lexer::token;
 
-pub use self::parser::grab_smaragdine_parser;
+pub use self::parser::{grab_smaragdine_parser, lex_source, parse_tokens, parse_source};
@@ -1,3 +1,5 @@
+use std::fmt;
+
 #[derive(Debug, Clone)]
 pub enum NodeType {
     StringLiteral(String),
@@ -5,6 +7,10 @@ pub enum NodeType {
     IntLiteral(i32),
     FloatLiteral(f32),
     BooleanLiteral(bool),
+    ListLiteral(Vec<Node>),
+    MapLiteral(Vec<(Node, Node)>),
+    /// `target[index]`
+    Index(Box<Node>, Box<Node>),
 }
 
 #[derive(Debug, Clone)]
@@ -22,4 +28,31 @@ impl Node {
     pub fn node_type(&self) -> &NodeType {
         &self.node_type
     }
-}
\ No newline at end of file
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.node_type {
+            NodeType::StringLiteral(ref s) => write!(f, "(string {:?})", s),
+            NodeType::CharLiteral(c)       => write!(f, "(char {:?})", c),
+            NodeType::IntLiteral(i)        => write!(f, "(int {})", i),
+            NodeType::FloatLiteral(n)      => write!(f, "(float {:?})", n),
+            NodeType::BooleanLiteral(b)    => write!(f, "(bool {})", b),
+            NodeType::ListLiteral(ref items) => {
+                write!(f, "(list")?;
+                for item in items {
+                    write!(f, " {}", item)?;
+                }
+                write!(f, ")")
+            },
+            NodeType::MapLiteral(ref entries) => {
+                write!(f, "(map")?;
+                for &(ref key, ref value) in entries {
+                    write!(f, " ({} {})", key, value)?;
+                }
+                write!(f, ")")
+            },
+            NodeType::Index(ref target, ref index) => write!(f, "(index {} {})", target, index),
+        }
+    }
+}
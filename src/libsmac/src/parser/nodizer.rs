@@ -1,27 +1,40 @@
+use diagnostic::Diagnostic;
 use parser::matcher::Matcher;
 use parser::node::Node;
 use parser::token::Token;
 
+/// How deeply lists, maps and indexes may nest
+pub const MAX_DEPTH: usize = 256;
+
 pub struct Snapshot {
-    index: usize,
+    index:       usize,
+    diagnostics: usize,
 }
 
 impl Snapshot {
-    pub fn new(index: usize) -> Snapshot {
+    pub fn new(index: usize, diagnostics: usize) -> Snapshot {
         Snapshot {
-            index: index,
+            index:       index,
+            diagnostics: diagnostics,
         }
     }
 
     pub fn index(&self) -> usize {
         self.index
     }
+
+    /// Number of diagnostics reported when the snapshot was taken
+    pub fn diagnostics(&self) -> usize {
+        self.diagnostics
+    }
 }
 
 pub struct Nodizer {
-    index:     usize,
-    items:     Vec<Token>,
-    snapshots: Vec<Snapshot>
+    index:       usize,
+    items:       Vec<Token>,
+    snapshots:   Vec<Snapshot>,
+    diagnostics: Vec<Diagnostic>,
+    depth:       usize,
 }
 
 impl Iterator for Nodizer {
@@ -35,9 +48,11 @@ impl Iterator for Nodizer {
 impl Nodizer {
     pub fn new(items: Vec<Token>) -> Nodizer {
         Nodizer {
-            index:     0,
-            items:     items,
-            snapshots: Vec::new(),
+            index:       0,
+            items:       items,
+            snapshots:   Vec::new(),
+            diagnostics: Vec::new(),
+            depth:       0,
         }
     }
 
@@ -60,6 +75,14 @@ impl Nodizer {
         Some(&self.items[self.index + n])
     }
 
+    /// The token most recently read
+    pub fn last(&self) -> Option<&Token> {
+        if self.index == 0 {
+            return None
+        }
+        Some(&self.items[self.index - 1])
+    }
+
     pub fn read(&mut self) -> Option<&Token> {
         if self.end() {
             return None;
@@ -73,7 +96,7 @@ impl Nodizer {
     }
 
     pub fn take_snapshot(&mut self) {
-        self.snapshots.push(Snapshot::new(self.index));
+        self.snapshots.push(Snapshot::new(self.index, self.diagnostics.len()));
     }
 
     pub fn peek_snapshot(&self) -> Option<&Snapshot> {
@@ -83,12 +106,33 @@ impl Nodizer {
     pub fn rollback_snapshot(&mut self) {
         let snapshot = self.snapshots.pop().unwrap();
         self.index   = snapshot.index();
+        self.diagnostics.truncate(snapshot.diagnostics());
     }
 
     pub fn commit_snapshot(&mut self) {
         self.snapshots.pop();
     }
 
+    /// Records an error. Diagnostics reported by a matcher
+    /// are discarded if its snapshot is rolled back.
+    pub fn report(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Enters a bracket, unless that would nest deeper than `MAX_DEPTH`
+    pub fn enter(&mut self) -> bool {
+        if self.depth >= MAX_DEPTH {
+            return false
+        }
+        self.depth += 1;
+        true
+    }
+
+    /// Leaves a bracket entered with `enter`
+    pub fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
     pub fn try_match_node(&mut self, matcher: &Matcher) -> Option<Node> {
         if self.end() {
             return None
@@ -110,4 +154,8 @@ impl Nodizer {
     pub fn index(&self) -> &usize {
         &self.index
     }
+
+    pub fn diagnostics(&self) -> &Vec<Diagnostic> {
+        &self.diagnostics
+    }
 }
\ No newline at end of file
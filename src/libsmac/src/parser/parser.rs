@@ -1,19 +1,55 @@
+use diagnostic::{Code, Diagnostic};
+use lexer::{lex_branch, flatten_branch};
+use lexer::block_tree::BlockTree;
 use parser::nodizer::Nodizer;
 use parser::matcher::*;
 use parser::node::Node;
 
-use parser::token::Token;
+use parser::token::{Token, TokenType};
 
 pub fn grab_smaragdine_parser(data: Vec<Token>) -> Parser {
     let nodizer = Nodizer::new(data);
     let mut parser = Parser::new(nodizer);
 
-    let matcher_literal = LiteralMatcher {};
+    let matcher_expression = ExpressionMatcher {};
 
-    parser.matchers_mut().push(Box::new(matcher_literal));
+    parser.matchers_mut().push(Box::new(matcher_expression));
     parser
 }
 
+/// Lexes `source` block by block, each indented block becoming a single
+/// `Block` token, as `parse_tokens` expects
+pub fn lex_source(source: &str) -> (Vec<Token>, Vec<Diagnostic>) {
+    let mut block_tree  = BlockTree::new(source, 0);
+    let indents         = block_tree.collect_indents();
+    let mut diagnostics = Vec::new();
+
+    let lexed_root = lex_branch(&block_tree.tree(&indents), &mut diagnostics);
+
+    (flatten_branch(&lexed_root), diagnostics)
+}
+
+/// Parses tokens from `lex_source`, returning the nodes and, with the
+/// `lexing` diagnostics, those of both phases in source order
+pub fn parse_tokens(tokens: Vec<Token>, lexing: Vec<Diagnostic>) -> (Vec<Node>, Vec<Diagnostic>) {
+    let mut diagnostics = lexing;
+    let mut parser      = grab_smaragdine_parser(tokens);
+    let nodes           = parser.by_ref().collect();
+
+    diagnostics.extend(parser.diagnostics().iter().cloned());
+    diagnostics.sort_by_key(|d| (d.position().line, d.position().col));
+
+    (nodes, diagnostics)
+}
+
+/// Lexes `source` block by block and parses it, returning the nodes and
+/// the diagnostics of both phases in source order
+pub fn parse_source(source: &str) -> (Vec<Node>, Vec<Diagnostic>) {
+    let (tokens, diagnostics) = lex_source(source);
+
+    parse_tokens(tokens, diagnostics)
+}
+
 pub struct Parser {
     nodizer: Nodizer,
     matchers: Vec<Box<Matcher>>,
@@ -37,6 +73,10 @@ impl Parser {
         None
     }
 
+    pub fn diagnostics(&self) -> &Vec<Diagnostic> {
+        self.nodizer.diagnostics()
+    }
+
     pub fn matchers(&self) -> &Vec<Box<Matcher>> {
         &self.matchers
     }
@@ -50,10 +90,21 @@ impl Iterator for Parser {
     type Item = Node;
 
     fn next(&mut self) -> Option<Node> {
-        if self.nodizer.end() {
-            None
-        } else {
-            Some(self.match_node().unwrap())
+        while !self.nodizer.end() {
+            match self.match_node() {
+                Some(n) => return Some(n),
+                None    => (),
+            }
+
+            // Nothing matched, skip the offending token
+            let token   = self.nodizer.next().unwrap();
+            let message = match *token.token_type() {
+                TokenType::Block(_) => "unexpected indented block".to_string(),
+                ref t               => format!("unexpected {:?} `{}`", t, token.content()),
+            };
+
+            self.nodizer.report(Diagnostic::new(Code::UnexpectedToken, message, *token.position(), *token.end()));
         }
+        None
     }
 }
\ No newline at end of file
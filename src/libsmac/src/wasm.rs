@@ -0,0 +1,107 @@
+use diagnostic::{Diagnostic, Severity};
+use highlight;
+use lexer::{Lexer, Token};
+use lexer::token::TokenPosition;
+use parser::parse_source;
+use parser::node::{Node, NodeType};
+
+use js_sys::{Array, Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+fn object(fields: Vec<(&str, JsValue)>) -> JsValue {
+    let object = Object::new();
+
+    for (key, value) in fields {
+        // Setting a property of a fresh plain object can't fail
+        let _ = Reflect::set(&object, &JsValue::from_str(key), &value);
+    }
+
+    object.into()
+}
+
+fn array<T, F: Fn(&T) -> JsValue>(items: &[T], f: F) -> JsValue {
+    let array = Array::new();
+
+    for item in items {
+        array.push(&f(item));
+    }
+
+    array.into()
+}
+
+/// `{line, col}`, with 1-based lines and 0-based columns as everywhere else
+fn position(pos: &TokenPosition) -> JsValue {
+    object(vec![("line", (pos.line as f64).into()), ("col", (pos.col as f64).into())])
+}
+
+fn token(t: &Token) -> JsValue {
+    object(vec![("kind", format!("{:?}", t.token_type()).into()),
+                ("content", t.content().as_str().into()),
+                ("start", position(t.position())),
+                ("end", position(t.end()))])
+}
+
+fn node(n: &Node) -> JsValue {
+    let (kind, value): (&str, JsValue) = match *n.node_type() {
+        NodeType::StringLiteral(ref s) => ("StringLiteral", s.as_str().into()),
+        NodeType::CharLiteral(c)       => ("CharLiteral", c.to_string().into()),
+        NodeType::IntLiteral(i)        => ("IntLiteral", (i as f64).into()),
+        NodeType::FloatLiteral(f)      => ("FloatLiteral", (f as f64).into()),
+        NodeType::BooleanLiteral(b)    => ("BooleanLiteral", b.into()),
+        NodeType::ListLiteral(ref items) => ("ListLiteral", array(items, node)),
+        NodeType::MapLiteral(ref entries) => {
+            ("MapLiteral", array(entries, |&(ref key, ref value)| object(vec![("key", node(key)), ("value", node(value))])))
+        },
+        NodeType::Index(ref target, ref index) => {
+            ("Index", object(vec![("target", node(target)), ("index", node(index))]))
+        },
+    };
+
+    object(vec![("kind", kind.into()), ("value", value)])
+}
+
+fn diagnostic(d: &Diagnostic) -> JsValue {
+    let severity = match d.severity() {
+        Severity::Error   => "error",
+        Severity::Warning => "warning",
+    };
+
+    object(vec![("severity", severity.into()),
+                ("code", d.code().map_or(JsValue::NULL, |c| c.id().into())),
+                ("message", d.message().as_str().into()),
+                ("start", position(d.position())),
+                ("end", position(d.end()))])
+}
+
+/// `{tokens, diagnostics}` for `source`
+#[wasm_bindgen]
+pub fn lex(source: &str) -> JsValue {
+    let (tokens, diagnostics) = Lexer::tokenize(source);
+
+    object(vec![("tokens", array(&tokens, token)), ("diagnostics", array(&diagnostics, diagnostic))])
+}
+
+/// `{nodes, diagnostics}` for `source`. Nodes are `{kind, value}` and,
+/// unlike tokens and diagnostics, carry no positions.
+#[wasm_bindgen]
+pub fn parse(source: &str) -> JsValue {
+    let (nodes, diagnostics) = parse_source(source);
+
+    object(vec![("nodes", array(&nodes, node)), ("diagnostics", array(&diagnostics, diagnostic))])
+}
+
+/// The diagnostics `smac check` reports for `source`
+#[wasm_bindgen]
+pub fn check(source: &str) -> JsValue {
+    array(&parse_source(source).1, diagnostic)
+}
+
+/// `{group, text}` pieces of `source`, as split by `highlight::highlight`;
+/// `group` is null for whitespace and characters that don't lex
+#[wasm_bindgen]
+pub fn highlight(source: &str) -> JsValue {
+    array(&highlight::highlight(source), |&(group, ref text)| {
+        let group = group.map_or(JsValue::NULL, |g| g.name().into());
+        object(vec![("group", group), ("text", text.as_str().into())])
+    })
+}
@@ -0,0 +1,134 @@
+use Error;
+use toml::{self, Value};
+
+use libsmac::formatter;
+use libsmac::lint::{self, Linter, MagicNumbers};
+
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// File holding the `smac fmt` settings
+pub const FORMATTER_CONFIG: &'static str = "smaragdine.fmt.toml";
+
+/// File holding the `smac lint` settings
+pub const LINT_CONFIG: &'static str = "smaragdine.lint.toml";
+
+/// Searches `start` and each of its ancestors for a file called `name`.
+pub fn find(start: &Path, name: &str) -> Option<PathBuf> {
+    let mut dir = Some(start);
+
+    while let Some(d) = dir {
+        let candidate = d.join(name);
+        if candidate.is_file() {
+            return Some(candidate)
+        }
+        dir = d.parent();
+    }
+
+    None
+}
+
+/// Directory to start looking for configuration from, for the input at `path`
+pub fn start_dir(path: &str) -> PathBuf {
+    let current = env::current_dir().unwrap_or(PathBuf::from("."));
+
+    if path == "-" {
+        return current
+    }
+
+    match Path::new(path).parent() {
+        Some(parent) if parent != Path::new("") => current.join(parent),
+        _                                       => current,
+    }
+}
+
+/// Reads and parses the TOML file at `path`
+pub fn read(path: &Path) -> Result<Vec<(String, Value)>, Error> {
+    let mut text = String::new();
+
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut text))
+        .map_err(|e| Error::Io(format!("could not read `{}`: {}", path.display(), e)))?;
+
+    toml::parse(&text).map_err(|e| Error::Usage(format!("{}: {}", path.display(), e)))
+}
+
+/// Loads the formatter settings that apply to the input at `path`,
+/// falling back to the defaults when there is no configuration file.
+/// There is no quote style setting, as `"` and `'` quote different
+/// literals.
+pub fn formatter(path: &str) -> Result<formatter::Config, Error> {
+    let mut config = formatter::Config::default();
+
+    let file = match find(&start_dir(path), FORMATTER_CONFIG) {
+        Some(file) => file,
+        None       => return Ok(config),
+    };
+
+    for (key, value) in read(&file)? {
+        match (key.as_str(), value) {
+            ("indent_width", Value::Integer(n)) if n > 0    => config.indent_width = n as usize,
+            ("max_line_length", Value::Integer(n)) if n > 0 => config.max_line_length = n as usize,
+            ("trailing_comma", Value::Boolean(b))           => config.trailing_comma = b,
+            (key @ "indent_width", _) | (key @ "max_line_length", _) => {
+                return Err(Error::Usage(format!("{}: `{}` must be a positive integer", file.display(), key)))
+            },
+            ("trailing_comma", _) => {
+                return Err(Error::Usage(format!("{}: `trailing_comma` must be a boolean", file.display())))
+            },
+            (key, _) => {
+                return Err(Error::Usage(format!("{}: unknown setting `{}`", file.display(), key)))
+            },
+        }
+    }
+
+    Ok(config)
+}
+
+/// Builds the linter that applies to the input at `path`. Rules are
+/// disabled with `<rule> = false`, naming the rule or its code, and
+/// the numbers `magic_numbers` accepts are set with `allowed` in its
+/// table.
+pub fn linter(path: &str) -> Result<Linter, Error> {
+    let mut linter = lint::grab_smaragdine_linter();
+
+    let file = match find(&start_dir(path), LINT_CONFIG) {
+        Some(file) => file,
+        None       => return Ok(linter),
+    };
+    let known = linter.rules().iter().map(|r| (r.name(), r.code().id())).collect::<Vec<_>>();
+
+    for (key, value) in read(&file)? {
+        match (key.as_str(), value) {
+            ("magic_numbers.allowed", Value::Array(items)) => {
+                let mut allowed = Vec::new();
+                for item in items {
+                    match item {
+                        Value::Integer(n) => allowed.push(n),
+                        _                 => {
+                            return Err(Error::Usage(format!("{}: `magic_numbers.allowed` must hold integers", file.display())))
+                        },
+                    }
+                }
+
+                // Top-level keys come before tables, so a disabled rule stays disabled
+                if linter.rules().iter().any(|r| r.name() == "magic_numbers") {
+                    linter.rules_mut().retain(|r| r.name() != "magic_numbers");
+                    linter.rules_mut().push(Box::new(MagicNumbers { allowed: allowed }));
+                }
+            },
+            (rule, Value::Boolean(enabled)) if known.iter().any(|&(name, id)| rule == name || rule == id) => {
+                if !enabled {
+                    linter.rules_mut().retain(|r| r.name() != rule && r.code().id() != rule)
+                }
+            },
+            (key, _) => {
+                return Err(Error::Usage(format!("{}: unknown setting `{}`", file.display(), key)))
+            },
+        }
+    }
+
+    Ok(linter)
+}
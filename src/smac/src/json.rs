@@ -0,0 +1,325 @@
+use libsmac::diagnostic::Diagnostic;
+use libsmac::grammar::Rule;
+use libsmac::lexer::Token;
+use libsmac::lexer::token::TokenPosition;
+use libsmac::parser::node::{Node, NodeType};
+
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Quotes and escapes `s` as a JSON string.
+pub fn string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"'  => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+
+    out
+}
+
+pub fn position(pos: &TokenPosition) -> String {
+    format!("{{\"line\": {}, \"col\": {}}}", pos.line, pos.col)
+}
+
+pub fn token(token: &Token) -> String {
+    format!("{{\"kind\": {}, \"content\": {}, \"start\": {}, \"end\": {}}}",
+            string(&format!("{:?}", token.token_type())),
+            string(token.content()),
+            position(token.position()),
+            position(token.end()))
+}
+
+pub fn node(n: &Node) -> String {
+    let (kind, value) = match *n.node_type() {
+        NodeType::StringLiteral(ref s) => ("StringLiteral", string(s)),
+        NodeType::CharLiteral(c)       => ("CharLiteral", string(&c.to_string())),
+        NodeType::IntLiteral(i)        => ("IntLiteral", i.to_string()),
+        NodeType::FloatLiteral(n)      => ("FloatLiteral", if n.is_finite() { format!("{:?}", n) } else { "null".to_string() }),
+        NodeType::BooleanLiteral(b)    => ("BooleanLiteral", b.to_string()),
+        NodeType::ListLiteral(ref items) => {
+            let items: Vec<_> = items.iter().map(node).collect();
+            ("ListLiteral", format!("[{}]", items.join(", ")))
+        },
+        NodeType::MapLiteral(ref entries) => {
+            let entries: Vec<_> = entries.iter()
+                                         .map(|&(ref key, ref value)| object(&[("key", node(key)), ("value", node(value))]))
+                                         .collect();
+            ("MapLiteral", format!("[{}]", entries.join(", ")))
+        },
+        NodeType::Index(ref target, ref index) => {
+            ("Index", object(&[("target", node(target)), ("index", node(index))]))
+        },
+    };
+
+    format!("{{\"kind\": {}, \"value\": {}}}", string(kind), value)
+}
+
+pub fn diagnostic(d: &Diagnostic) -> String {
+    format!("{{\"severity\": {}, \"code\": {}, \"message\": {}, \"start\": {}, \"end\": {}}}",
+            string(&d.severity().to_string()),
+            d.code().map_or("null".to_string(), |c| string(c.id())),
+            string(d.message()),
+            position(d.position()),
+            position(d.end()))
+}
+
+pub fn rule(rule: &Rule) -> String {
+    let alternatives: Vec<_> = rule.alternatives.iter().map(|a| string(a)).collect();
+
+    format!("{{\"name\": {}, \"alternatives\": [{}]}}", string(rule.name), alternatives.join(", "))
+}
+
+/// Renders already-serialised values as a JSON array, one element per line.
+pub fn array(items: &[String]) -> String {
+    if items.is_empty() {
+        return "[]".to_string()
+    }
+
+    format!("[\n  {}\n]", items.join(",\n  "))
+}
+
+/// Renders already-serialised values as a JSON object on a single line.
+pub fn object(fields: &[(&str, String)]) -> String {
+    let fields: Vec<_> = fields.iter().map(|&(key, ref value)| format!("{}: {}", string(key), value)).collect();
+
+    format!("{{{}}}", fields.join(", "))
+}
+
+/// A parsed JSON value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// The field `key` of an object
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match *self {
+            Value::Object(ref fields) => fields.iter().find(|&&(ref k, _)| k == key).map(|&(_, ref v)| v),
+            _                         => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::String(ref s) => Some(s),
+            _                    => None,
+        }
+    }
+}
+
+/// Serialises the value compactly
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Null          => write!(f, "null"),
+            Value::Boolean(b)    => write!(f, "{}", b),
+            Value::Number(n)     => write!(f, "{}", n),
+            Value::String(ref s) => write!(f, "{}", string(s)),
+            Value::Array(ref items) => {
+                let items: Vec<_> = items.iter().map(|v| v.to_string()).collect();
+                write!(f, "[{}]", items.join(", "))
+            },
+            Value::Object(ref fields) => {
+                let fields: Vec<_> = fields.iter().map(|&(ref k, ref v)| (k.as_str(), v.to_string())).collect();
+                write!(f, "{}", object(&fields))
+            },
+        }
+    }
+}
+
+/// Deepest nesting of arrays and objects `parse` accepts
+const MAX_DEPTH: usize = 256;
+
+/// Parses a JSON document
+pub fn parse(text: &str) -> Result<Value, String> {
+    let mut chars = text.chars().peekable();
+    let value     = parse_value(&mut chars, 0)?;
+
+    skip_whitespace(&mut chars);
+    match chars.next() {
+        None    => Ok(value),
+        Some(c) => Err(format!("unexpected `{}` after value", c)),
+    }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.peek().map_or(false, |c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+fn expect(chars: &mut Peekable<Chars>, word: &str) -> Result<(), String> {
+    for expected in word.chars() {
+        if chars.next() != Some(expected) {
+            return Err(format!("expected `{}`", word))
+        }
+    }
+    Ok(())
+}
+
+fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    expect(chars, "\"")?;
+
+    let mut string = String::new();
+    loop {
+        match chars.next() {
+            Some('"')  => return Ok(string),
+            Some('\\') => match chars.next() {
+                Some('n') => string.push('\n'),
+                Some('r') => string.push('\r'),
+                Some('t') => string.push('\t'),
+                Some('b') => string.push('\u{8}'),
+                Some('f') => string.push('\u{c}'),
+                Some('u') => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    let unit        = u32::from_str_radix(&hex, 16).map_err(|_| format!("invalid escape `\\u{}`", hex))?;
+
+                    // Surrogate pairs are not combined
+                    string.push(::std::char::from_u32(unit).unwrap_or('\u{fffd}'))
+                },
+                Some(c) => string.push(c),
+                None    => return Err("unterminated string".to_string()),
+            },
+            Some(c) => string.push(c),
+            None    => return Err("unterminated string".to_string()),
+        }
+    }
+}
+
+fn parse_value(chars: &mut Peekable<Chars>, depth: usize) -> Result<Value, String> {
+    skip_whitespace(chars);
+
+    if depth > MAX_DEPTH && chars.peek().map_or(false, |&c| c == '[' || c == '{') {
+        return Err(format!("arrays and objects nest more than {} deep", MAX_DEPTH))
+    }
+
+    match chars.peek().cloned() {
+        Some('n') => expect(chars, "null").map(|_| Value::Null),
+        Some('t') => expect(chars, "true").map(|_| Value::Boolean(true)),
+        Some('f') => expect(chars, "false").map(|_| Value::Boolean(false)),
+        Some('"') => parse_string(chars).map(Value::String),
+
+        Some('[') => {
+            chars.next();
+
+            let mut items = Vec::new();
+            skip_whitespace(chars);
+            if chars.peek() == Some(&']') {
+                chars.next();
+                return Ok(Value::Array(items))
+            }
+
+            loop {
+                items.push(parse_value(chars, depth + 1)?);
+
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => (),
+                    Some(']') => return Ok(Value::Array(items)),
+                    _         => return Err("expected `,` or `]` in array".to_string()),
+                }
+            }
+        },
+
+        Some('{') => {
+            chars.next();
+
+            let mut fields = Vec::new();
+            skip_whitespace(chars);
+            if chars.peek() == Some(&'}') {
+                chars.next();
+                return Ok(Value::Object(fields))
+            }
+
+            loop {
+                skip_whitespace(chars);
+                let key = parse_string(chars)?;
+
+                skip_whitespace(chars);
+                expect(chars, ":")?;
+                fields.push((key, parse_value(chars, depth + 1)?));
+
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => (),
+                    Some('}') => return Ok(Value::Object(fields)),
+                    _         => return Err("expected `,` or `}` in object".to_string()),
+                }
+            }
+        },
+
+        Some(_) => {
+            let mut number = String::new();
+            while chars.peek().map_or(false, |&c| c.is_digit(10) || "+-.eE".contains(c)) {
+                number.push(chars.next().unwrap());
+            }
+
+            number.parse::<f64>()
+                  .map(Value::Number)
+                  .map_err(|_| format!("invalid value `{}`", number))
+        },
+
+        None => Err("missing value".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, string, Value};
+
+    #[test]
+    fn parse_values() {
+        let value = parse(" {\"a\": [1, -2.5e1, true, false, null], \"b\": \"q\\\"\\n\\u00e9\", \"c\": {}} ").unwrap();
+
+        assert_eq!(value.get("a"), Some(&Value::Array(vec![Value::Number(1.0),
+                                                             Value::Number(-25.0),
+                                                             Value::Boolean(true),
+                                                             Value::Boolean(false),
+                                                             Value::Null])));
+        assert_eq!(value.get("b").and_then(Value::as_str), Some("q\"\né"));
+        assert_eq!(value.get("c"), Some(&Value::Object(Vec::new())));
+        assert_eq!(value.get("d"), None);
+        assert_eq!(parse(&value.to_string()), Ok(value));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |text: &str| parse(text).unwrap_err();
+
+        assert_eq!(error(""), "missing value");
+        assert_eq!(error("nul"), "expected `null`");
+        assert_eq!(error("\"open"), "unterminated string");
+        assert_eq!(error("\"\\uzz\""), "invalid escape `\\uzz\"`");
+        assert_eq!(error("[1 2]"), "expected `,` or `]` in array");
+        assert_eq!(error("{\"a\" 1}"), "expected `:`");
+        assert_eq!(error("{\"a\": 1 \"b\": 2}"), "expected `,` or `}` in object");
+        assert_eq!(error("{1: 2}"), "expected `\"`");
+        assert_eq!(error("1 2"), "unexpected `2` after value");
+        assert_eq!(error("-"), "invalid value `-`");
+        assert_eq!(error(&"[".repeat(200_000)), "arrays and objects nest more than 256 deep");
+        assert!(parse(&format!("{}{}", "[".repeat(257), "]".repeat(257))).is_ok());
+    }
+
+    #[test]
+    fn string_escapes() {
+        assert_eq!(string("a\"\\\n\u{1}"), "\"a\\\"\\\\\\n\\u0001\"");
+    }
+}
@@ -0,0 +1,276 @@
+use {config, parse, Error, Options};
+use json::{self, Value};
+
+use libsmac::diagnostic::{Diagnostic, Severity};
+use libsmac::formatter;
+use libsmac::lexer::token::TokenPosition;
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Read, Write};
+use std::process;
+
+/// JSON-RPC error codes
+const PARSE_ERROR: i32      = -32700;
+const METHOD_NOT_FOUND: i32 = -32601;
+
+/// Largest message read, in bytes. Longer ones are skipped.
+const MAX_MESSAGE: usize = 16 << 20;
+
+/// What the server supports: whole-document sync, diagnostics and
+/// formatting. Definitions and hovers need a resolver and a type checker.
+const CAPABILITIES: &'static str = "{\"capabilities\": {\"textDocumentSync\": 1, \"documentFormattingProvider\": true}, \
+                                     \"serverInfo\": {\"name\": \"smac\"}}";
+
+/// Serves the Language Server Protocol over stdin and stdout until the
+/// client sends `exit`.
+pub fn lsp() -> Result<(), Error> {
+    let stdin     = io::stdin();
+    let mut input = stdin.lock();
+
+    let mut documents = HashMap::new();
+    let mut shutdown  = false;
+
+    while let Some(message) = read_message(&mut input)? {
+        // A malformed message is answered like malformed JSON, and the
+        // server carries on with the next one
+        let message = match message.and_then(|m| json::parse(&m)) {
+            Ok(message) => message,
+            Err(e)      => {
+                eprintln!("smac lsp: ignoring a malformed message: {}", e);
+                error(&Value::Null, PARSE_ERROR, &e)?;
+                continue
+            },
+        };
+
+        let id     = message.get("id").cloned();
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let uri    = params.get("textDocument")
+                           .and_then(|d| d.get("uri"))
+                           .and_then(Value::as_str)
+                           .unwrap_or("")
+                           .to_string();
+
+        match message.get("method").and_then(Value::as_str).unwrap_or("") {
+            "initialize" => respond(&id, CAPABILITIES)?,
+            "shutdown"   => {
+                shutdown = true;
+                respond(&id, "null")?
+            },
+            "exit" => process::exit(if shutdown { 0 } else { 1 }),
+
+            "textDocument/didOpen" => {
+                let text = params.get("textDocument").and_then(|d| d.get("text")).and_then(Value::as_str);
+
+                documents.insert(uri.clone(), text.unwrap_or("").to_string());
+                publish(&uri, &documents[&uri])?
+            },
+            "textDocument/didChange" => {
+                // With full sync the last change holds the whole document
+                let text = match params.get("contentChanges") {
+                    Some(&Value::Array(ref changes)) => changes.last().and_then(|c| c.get("text")).and_then(Value::as_str),
+                    _                                => None,
+                };
+
+                if let Some(text) = text {
+                    documents.insert(uri.clone(), text.to_string());
+                    publish(&uri, &documents[&uri])?
+                }
+            },
+            "textDocument/didClose" => {
+                documents.remove(&uri);
+                notify("textDocument/publishDiagnostics",
+                       &json::object(&[("uri", json::string(&uri)), ("diagnostics", "[]".to_string())]))?
+            },
+            "textDocument/formatting" => {
+                let text = documents.get(&uri).map_or("", |t| t.as_str());
+                respond(&id, &format_document(&uri, text))?
+            },
+
+            method => if id.is_some() {
+                error(&id.unwrap(), METHOD_NOT_FOUND, &format!("`{}` is not supported", method))?
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the body of the next message, or `None` at end of input. A
+/// message that can't be read as text is an `Err` describing it.
+fn read_message<R: BufRead>(input: &mut R) -> Result<Option<Result<String, String>>, Error> {
+    let fail = |e: io::Error| Error::Io(format!("could not read message: {}", e));
+
+    let mut length = None;
+
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header).map_err(&fail)? == 0 {
+            return Ok(None)
+        }
+
+        let header = header.trim();
+        if header.is_empty() {
+            break
+        }
+
+        let mut parts = header.splitn(2, ':');
+        if parts.next().map_or(false, |name| name.trim().eq_ignore_ascii_case("content-length")) {
+            length = parts.next().and_then(|n| n.trim().parse::<usize>().ok());
+        }
+    }
+
+    let length = match length {
+        Some(length) => length,
+        None         => return Ok(Some(Err("message without a Content-Length".to_string()))),
+    };
+
+    if length > MAX_MESSAGE {
+        io::copy(&mut Read::take(&mut *input, length as u64), &mut io::sink()).map_err(&fail)?;
+        return Ok(Some(Err(format!("message of {} bytes is over the limit of {}", length, MAX_MESSAGE))))
+    }
+
+    let mut body = vec![0; length];
+    input.read_exact(&mut body).map_err(&fail)?;
+
+    Ok(Some(String::from_utf8(body).map_err(|_| "message is not UTF-8".to_string())))
+}
+
+fn send(body: &str) -> Result<(), Error> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    write!(out, "Content-Length: {}\r\n\r\n{}", body.len(), body)
+        .and_then(|_| out.flush())
+        .map_err(|e| Error::Io(format!("could not send message: {}", e)))
+}
+
+fn respond(id: &Option<Value>, result: &str) -> Result<(), Error> {
+    let id = id.as_ref().unwrap_or(&Value::Null);
+
+    send(&json::object(&[("jsonrpc", json::string("2.0")), ("id", id.to_string()), ("result", result.to_string())]))
+}
+
+fn error(id: &Value, code: i32, message: &str) -> Result<(), Error> {
+    let error = json::object(&[("code", code.to_string()), ("message", json::string(message))]);
+
+    send(&json::object(&[("jsonrpc", json::string("2.0")), ("id", id.to_string()), ("error", error)]))
+}
+
+fn notify(method: &str, params: &str) -> Result<(), Error> {
+    send(&json::object(&[("jsonrpc", json::string("2.0")), ("method", json::string(method)), ("params", params.to_string())]))
+}
+
+/// A position as LSP has it, with 0-based lines. Columns count chars
+/// where LSP counts UTF-16 units, which only differs outside the BMP.
+fn position(pos: &TokenPosition) -> String {
+    json::object(&[("line", pos.line.saturating_sub(1).to_string()), ("character", pos.col.to_string())])
+}
+
+fn range(start: &TokenPosition, end: &TokenPosition) -> String {
+    json::object(&[("start", position(start)), ("end", position(end))])
+}
+
+fn diagnostic(d: &Diagnostic) -> String {
+    let severity = match d.severity() {
+        Severity::Error   => 1,
+        Severity::Warning => 2,
+    };
+
+    let mut fields = vec![("range", range(d.position(), d.end())),
+                          ("severity", severity.to_string()),
+                          ("source", json::string("smac")),
+                          ("message", json::string(d.message()))];
+
+    if let Some(code) = d.code() {
+        fields.push(("code", json::string(code.id())));
+    }
+
+    json::object(&fields)
+}
+
+/// Publishes what `smac check` reports for the document
+fn publish(uri: &str, text: &str) -> Result<(), Error> {
+    let (_, diagnostics) = parse(text, &Options::default());
+    let items: Vec<_>    = diagnostics.iter().map(diagnostic).collect();
+
+    notify("textDocument/publishDiagnostics",
+           &json::object(&[("uri", json::string(uri)), ("diagnostics", format!("[{}]", items.join(", ")))]))
+}
+
+/// The edits formatting the document: one replacing all of it, none if it
+/// is already formatted, or `null` if it has errors
+fn format_document(uri: &str, text: &str) -> String {
+    // Settings are looked up from the document's directory
+    let path = file_path(uri).unwrap_or("-".to_string());
+
+    let config = match config::formatter(&path) {
+        Ok(config) => config,
+        Err(_)     => return "null".to_string(),
+    };
+
+    match formatter::format(text, &config) {
+        Ok(ref formatted) if formatted == text => "[]".to_string(),
+        Ok(formatted) => {
+            let start = TokenPosition::new(1, 0);
+            let last  = text.split('\n').last().unwrap_or("");
+            let end   = TokenPosition::new(text.split('\n').count(), last.chars().count());
+
+            format!("[{}]", json::object(&[("range", range(&start, &end)), ("newText", json::string(&formatted))]))
+        },
+        Err(_) => "null".to_string(),
+    }
+}
+
+/// The path of a `file://` URI, percent-decoded
+fn file_path(uri: &str) -> Option<String> {
+    if !uri.starts_with("file://") {
+        return None
+    }
+
+    let encoded   = uri["file://".len()..].as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut i     = 0;
+
+    while i < encoded.len() {
+        if encoded[i] == b'%' {
+            let hex = encoded.get(i + 1..i + 3).and_then(|h| String::from_utf8(h.to_vec()).ok())?;
+            bytes.push(u8::from_str_radix(&hex, 16).ok()?);
+            i += 3;
+        } else {
+            bytes.push(encoded[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{file_path, format_document, read_message, MAX_MESSAGE};
+
+    #[test]
+    fn file_path_decodes_uris() {
+        assert_eq!(file_path("file:///home/me/my%20project/a%C3%A9.sm"), Some("/home/me/my project/aé.sm".to_string()));
+        assert_eq!(file_path("file:///a%2"), None);
+        assert_eq!(file_path("file:///a%FF"), None);
+        assert_eq!(file_path("untitled:1"), None);
+    }
+
+    #[test]
+    fn read_message_skips_long_messages() {
+        let input     = format!("Content-Length: {}\r\n\r\n{}Content-Length: 2\r\n\r\n{{}}", MAX_MESSAGE + 1, " ".repeat(MAX_MESSAGE + 1));
+        let mut input = input.as_bytes();
+
+        assert!(read_message(&mut input).unwrap().unwrap().is_err());
+        assert_eq!(read_message(&mut input).unwrap(), Some(Ok("{}".to_string())));
+        assert_eq!(read_message(&mut input).unwrap(), None);
+    }
+
+    #[test]
+    fn format_document_replaces_to_the_end() {
+        let edits = format_document("untitled:1", "x=1\ny=2");
+
+        assert!(edits.contains("\"end\": {\"line\": 1, \"character\": 3}"), "{}", edits);
+    }
+}
@@ -1,56 +1,668 @@
 extern crate libsmac;
+#[cfg(feature = "readline")]
+extern crate rustyline;
 
-use libsmac::lexer::block_tree;
-use libsmac::lexer::{grab_smaragdine_lexer, lex_branch, flatten_branch};
-use libsmac::parser::grab_smaragdine_parser;
+mod config;
+mod json;
+mod lsp;
+mod project;
+mod repl;
+mod serve;
+mod term;
+mod toml;
+mod watch;
+
+use libsmac::diagnostic::{self, Code, Diagnostic};
+use libsmac::formatter;
+use libsmac::grammar;
+use libsmac::highlight::{self, Group};
+use libsmac::lexer::{Lexer, Token};
+use libsmac::lexer::{SYMBOLS, OPERATORS, KEYWORDS, BOOLEANS};
+use libsmac::parser::parse_source;
+use libsmac::parser::node::Node;
+
+use term::{paint, Stream, Style};
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process;
+use std::time::Instant;
+
+const USAGE: &'static str = "usage: smac <command> [options] [<file>]
+       smac (build | run) [options] <project>
+       smac watch <path> -- <command> [options] [<file>]
+       smac repl
+       smac lsp
+       smac serve [<address>]
+       smac grammar [--format=<format>]
+       smac explain [<code>]
+
+Reads from standard input when <file> is `-` or omitted. A <project> is a
+directory holding a Smaragdine.toml; build and run compile every module of
+it and of the packages it depends on, and run then runs its entry point.
+
+commands:
+    tokens    print the token stream of <file>
+    ast       print the parsed tree of <file>
+    run       lex and parse <file>, printing the parsed nodes
+    build     emit the stages selected with --emit
+    check     report errors in <file> without running it
+    fmt       format <file> in place, or to stdout when reading stdin,
+              using the nearest smaragdine.fmt.toml
+    lint      warn about suspicious code in <file>, with the rules
+              configured by the nearest smaragdine.lint.toml
+    highlight print <file> with syntax highlighting, as selected by --format
+    watch     rerun <command> whenever <path>, or a .sm file beneath it, changes
+    repl      parse entries interactively
+    lsp       serve the Language Server Protocol over stdin and stdout
+    serve     serve the playground HTTP API on <address>, 127.0.0.1:8080 by
+              default: POST a source to /compile for its tokens, AST and
+              diagnostics as JSON
+    grammar   print the token and grammar definitions smac implements, as
+              ebnf (the default) or json
+    explain   describe the error or lint warning with <code>, like E0001 or
+              W0001, with an example of it and its fix; lists every code
+              when <code> is omitted
+
+options:
+    --color=<when>    color output: auto (the default), always or never;
+                      auto colors terminals unless NO_COLOR is set
+    --json            print machine-readable output (tokens, ast)
+    -v, --timings     report the time taken by each compiler phase, and
+                      how many tokens, nodes or lines it produced
+    --check           only check that <file> is formatted (fmt)
+    --format=<format> output format: ansi (the default) or html (highlight),
+                      ebnf (the default) or json (grammar)
+    --emit=<stages>   comma-separated list of tokens, ast, ir, bytecode, disasm
+    -o <path>         write each emitted stage to <path>.<stage>, or for a
+                      project to <path>/<package>/<module>.<stage>
+
+exit status:
+    0    success
+    1    <file> has errors or warnings, or is not formatted
+    2    invalid usage
+    3    reading input or writing output failed";
+
+/// An intermediate artifact of the pipeline that `build` can emit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Emit {
+    Tokens,
+    Ast,
+    Ir,
+    Bytecode,
+    Disasm,
+}
+
+impl Emit {
+    fn from_name(name: &str) -> Option<Emit> {
+        match name {
+            "tokens"   => Some(Emit::Tokens),
+            "ast"      => Some(Emit::Ast),
+            "ir"       => Some(Emit::Ir),
+            "bytecode" => Some(Emit::Bytecode),
+            "disasm"   => Some(Emit::Disasm),
+            _          => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            Emit::Tokens   => "tokens",
+            Emit::Ast      => "ast",
+            Emit::Ir       => "ir",
+            Emit::Bytecode => "bytecode",
+            Emit::Disasm   => "disasm",
+        }
+    }
+}
+
+/// Why a command failed, which decides the exit status.
+#[derive(Debug)]
+pub enum Error {
+    /// Bad arguments or an unsupported request
+    Usage(String),
+    /// Reading the input or writing output failed
+    Io(String),
+    /// The source had this many errors, all of them already reported
+    Compile(usize),
+    /// `fmt --check` found this file not formatted
+    Unformatted(String),
+    /// `lint` found this many warnings, all of them already reported
+    Lints(usize),
+}
+
+impl Error {
+    fn exit_code(&self) -> i32 {
+        match *self {
+            Error::Compile(_) | Error::Unformatted(_) | Error::Lints(_) => 1,
+            Error::Usage(_)                                             => 2,
+            Error::Io(_)                                                => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Options {
+    json:    bool,
+    check:   bool,
+    timings: bool,
+    format:  Option<String>,
+    emit:    Vec<Emit>,
+    output:  Option<String>,
+}
+
+type Command = fn(&str, &str, &Options) -> Result<(), Error>;
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2)
+}
+
+fn fail(error: Error) -> ! {
+    let message = match error {
+        Error::Usage(ref message) | Error::Io(ref message) => message.clone(),
+        Error::Compile(1)                                  => "aborting due to previous error".to_string(),
+        Error::Compile(n)                                  => format!("aborting due to {} previous errors", n),
+        Error::Unformatted(ref name)                       => format!("`{}` is not formatted", name),
+        Error::Lints(1)                                    => "found 1 lint warning".to_string(),
+        Error::Lints(n)                                    => format!("found {} lint warnings", n),
+    };
+
+    eprintln!("{} {}", paint(Stream::Stderr, Style::Red, "error:"), message);
+    process::exit(error.exit_code())
+}
+
+fn read_source(path: &str) -> Result<String, Error> {
+    let mut source = String::new();
+
+    if path == "-" {
+        return match io::stdin().read_to_string(&mut source) {
+            Ok(_)  => Ok(source),
+            Err(e) => Err(Error::Io(format!("could not read standard input: {}", e))),
+        }
+    }
+
+    match File::open(path).and_then(|mut file| file.read_to_string(&mut source)) {
+        Ok(_)  => Ok(source),
+        Err(e) => Err(Error::Io(format!("could not read `{}`: {}", path, e))),
+    }
+}
+
+/// With `--timings`, reports that `phase` took since `start` and
+/// produced `count` of `unit`
+fn timing(options: &Options, phase: &str, start: Instant, count: usize, unit: &str) {
+    if !options.timings {
+        return
+    }
+
+    let elapsed = start.elapsed();
+    let ms      = elapsed.as_secs() as f64 * 1e3 + elapsed.subsec_nanos() as f64 / 1e6;
+
+    eprintln!("{} {:<10} {:>10.3}ms {:>8} {}", paint(Stream::Stderr, Style::Dim, "timing:"), phase, ms, count, unit)
+}
+
+fn lex(source: &str, options: &Options) -> (Vec<Token>, Vec<Diagnostic>) {
+    let start                 = Instant::now();
+    let (tokens, diagnostics) = Lexer::tokenize(source);
+
+    timing(options, "lexing", start, tokens.len(), "tokens");
+
+    (tokens, diagnostics)
+}
+
+/// Lexes and parses `source`, timed as one phase since `parse_source`
+/// lexes block by block as it goes
+fn parse(source: &str, options: &Options) -> (Vec<Node>, Vec<Diagnostic>) {
+    let start                = Instant::now();
+    let (nodes, diagnostics) = parse_source(source);
+
+    timing(options, "parsing", start, nodes.len(), "nodes");
+
+    (nodes, diagnostics)
+}
+
+/// Name to show for the input at `path`
+fn display_name(path: &str) -> &str {
+    if path == "-" { "<stdin>" } else { path }
+}
+
+/// Prints `diagnostics` to stderr, failing if there were any.
+fn report(path: &str, diagnostics: &[Diagnostic]) -> Result<(), Error> {
+    let name = display_name(path);
+
+    for d in diagnostics {
+        eprintln!("{}", term::diagnostic(name, d))
+    }
+
+    if diagnostics.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Compile(diagnostics.len()))
+    }
+}
+
+fn render_tokens(tokens: &[Token], options: &Options) -> String {
+    if options.json {
+        let items: Vec<_> = tokens.iter().map(json::token).collect();
+        return json::array(&items) + "\n"
+    }
+
+    let mut out = String::new();
+
+    for t in tokens {
+        let span = format!("{}:{}-{}:{}",
+                           t.position().line, t.position().col,
+                           t.end().line, t.end().col);
+        let kind = format!("{:?}", t.token_type());
+        // Padded apart from the color codes, which take no columns
+        let pad  = " ".repeat(16usize.saturating_sub(kind.len()));
+
+        out.push_str(&format!("{:<16} {}{} {:?}\n", span, paint(Stream::Stdout, Style::Cyan, &kind), pad, t.content()))
+    }
+
+    out
+}
+
+fn render_ast(nodes: &[Node], options: &Options) -> String {
+    if options.json {
+        let items: Vec<_> = nodes.iter().map(json::node).collect();
+        return json::array(&items) + "\n"
+    }
+
+    let mut out = String::new();
+
+    for n in nodes {
+        out.push_str(&format!("{}\n", n))
+    }
+
+    out
+}
+
+fn tokens(path: &str, source: &str, options: &Options) -> Result<(), Error> {
+    let (tokens, diagnostics) = lex(source, options);
+
+    print!("{}", render_tokens(&tokens, options));
+    report(path, &diagnostics)
+}
+
+fn ast(path: &str, source: &str, options: &Options) -> Result<(), Error> {
+    let (nodes, diagnostics) = parse(source, options);
+
+    print!("{}", render_ast(&nodes, options));
+    report(path, &diagnostics)
+}
+
+fn run(path: &str, source: &str, options: &Options) -> Result<(), Error> {
+    let (nodes, diagnostics) = parse(source, options);
+
+    report(path, &diagnostics)?;
+
+    for n in nodes {
+        println!("{:#?}", n)
+    }
+    Ok(())
+}
+
+fn check(path: &str, source: &str, options: &Options) -> Result<(), Error> {
+    let (_, diagnostics) = parse(source, options);
+
+    report(path, &diagnostics)
+}
+
+fn fmt(path: &str, source: &str, options: &Options) -> Result<(), Error> {
+    let config    = config::formatter(path)?;
+    let start     = Instant::now();
+    let formatted = match formatter::format(source, &config) {
+        Ok(formatted)    => formatted,
+        Err(diagnostics) => return report(path, &diagnostics),
+    };
+
+    timing(options, "formatting", start, formatted.lines().count(), "lines");
+
+    if options.check {
+        return if formatted == source {
+            Ok(())
+        } else {
+            Err(Error::Unformatted(display_name(path).to_string()))
+        }
+    }
+
+    if path == "-" {
+        print!("{}", formatted);
+        return Ok(())
+    }
+
+    if formatted != source {
+        File::create(path)
+            .and_then(|mut file| file.write_all(formatted.as_bytes()))
+            .map_err(|e| Error::Io(format!("could not write `{}`: {}", path, e)))?
+    }
+    Ok(())
+}
+
+fn lint(path: &str, source: &str, options: &Options) -> Result<(), Error> {
+    let linter                = config::linter(path)?;
+    let (tokens, diagnostics) = lex(source, options);
+
+    report(path, &diagnostics)?;
+
+    let start = Instant::now();
+    let lints = linter.lint(&tokens);
+    let name  = display_name(path);
+
+    timing(options, "linting", start, lints.len(), "warnings");
+
+    for d in &lints {
+        eprintln!("{}", term::diagnostic(name, d))
+    }
+
+    if lints.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Lints(lints.len()))
+    }
+}
+
+fn highlight(_path: &str, source: &str, options: &Options) -> Result<(), Error> {
+    let pieces = highlight::highlight(source);
+
+    match options.format.as_ref().map_or("ansi", |f| f.as_str()) {
+        "ansi" => {
+            for (group, text) in pieces {
+                let style = match group {
+                    Some(Group::Keyword)                    => Some(Style::Magenta),
+                    Some(Group::Boolean)                    => Some(Style::Yellow),
+                    Some(Group::Number)                     => Some(Style::Yellow),
+                    Some(Group::String) | Some(Group::Char) => Some(Style::Green),
+                    Some(Group::Operator)                   => Some(Style::Cyan),
+                    _                                       => None,
+                };
+
+                match style {
+                    Some(style) => print!("{}", paint(Stream::Stdout, style, &text)),
+                    None        => print!("{}", text),
+                }
+            }
+        },
+        "html" => {
+            let mut out = String::from("<pre class=\"smaragdine\"><code>");
+
+            for (group, text) in pieces {
+                let text = text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+
+                match group {
+                    Some(group) => out.push_str(&format!("<span class=\"sm-{}\">{}</span>", group.name(), text)),
+                    None        => out.push_str(&text),
+                }
+            }
+
+            println!("{}</code></pre>", out)
+        },
+        format => return Err(Error::Usage(format!("unknown highlight format `{}`; expected ansi or html", format))),
+    }
+
+    Ok(())
+}
+
+fn grammar(format: &str) -> Result<(), Error> {
+    let rules = grammar::rules();
+
+    match format {
+        "ebnf" => print!("{}", grammar::ebnf(&rules)),
+        "json" => {
+            let list = |items: &[&str]| {
+                let items: Vec<_> = items.iter().map(|i| json::string(i)).collect();
+                format!("[{}]", items.join(", "))
+            };
+            let rules: Vec<_> = rules.iter().map(json::rule).collect();
+            let tokens        = json::object(&[("keywords", list(KEYWORDS)),
+                                               ("booleans", list(BOOLEANS)),
+                                               ("operators", list(OPERATORS)),
+                                               ("symbols", list(SYMBOLS))]);
+
+            println!("{}", json::object(&[("tokens", tokens), ("rules", json::array(&rules))]))
+        },
+        format => return Err(Error::Usage(format!("unknown grammar format `{}`; expected ebnf or json", format))),
+    }
+
+    Ok(())
+}
+
+fn explain(id: Option<&str>) -> Result<(), Error> {
+    let id = match id {
+        Some(id) => id,
+        None     => {
+            for code in diagnostic::CODES {
+                println!("{}  {}", code.id(), code.explanation().summary);
+            }
+            return Ok(())
+        },
+    };
+
+    let code = match Code::from_id(id) {
+        Some(code) => code,
+        None       => return Err(Error::Usage(format!("unknown diagnostic code `{}`", id))),
+    };
+    let explanation = code.explanation();
+
+    let indent = |source: &str| format!("    {}", source.replace('\n', "\n    "));
+
+    let heading = format!("{}[{}]", code.severity(), explanation.id);
+    println!("{}: {}\n", paint(Stream::Stdout, Style::Bold, &heading), explanation.summary);
+    println!("{}\n", explanation.description);
+    println!("For example, this source raises it:\n\n{}\n", indent(explanation.example));
+    println!("and this fixes it:\n\n{}", indent(explanation.fix));
+
+    Ok(())
+}
+
+fn build(path: &str, source: &str, options: &Options) -> Result<(), Error> {
+    if options.emit.is_empty() {
+        return Err(Error::Usage("nothing to build; select stages with --emit".to_string()))
+    }
+
+    // There is no IR or bytecode compiler yet
+    if let Some(stage) = options.emit.iter().find(|&&s| s != Emit::Tokens && s != Emit::Ast) {
+        return Err(Error::Usage(format!("--emit={} is not supported yet", stage.name())))
+    }
+
+    for &stage in &options.emit {
+        let artifact = match stage {
+            Emit::Tokens => {
+                let (tokens, diagnostics) = lex(source, options);
+                report(path, &diagnostics)?;
+                render_tokens(&tokens, options)
+            },
+            _ => {
+                let (nodes, diagnostics) = parse(source, options);
+                report(path, &diagnostics)?;
+                render_ast(&nodes, options)
+            },
+        };
+
+        match options.output {
+            Some(ref stem) => {
+                let path = format!("{}.{}", stem, stage.name());
+
+                File::create(&path)
+                    .and_then(|mut file| file.write_all(artifact.as_bytes()))
+                    .map_err(|e| Error::Io(format!("could not write `{}`: {}", path, e)))?
+            },
+            None => print!("{}", artifact),
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs the command called `name` over the project at `root`, reporting
+/// the errors of every module before failing. `run` checks every module,
+/// then runs the entry point if there were no errors.
+fn project(root: &str, name: &str, command: Command, options: &Options) -> Result<(), Error> {
+    let modules = project::modules(Path::new(root))?;
+    let headers = name == "build" && options.output.is_none() && modules.len() > 1;
+
+    let mut errors = 0;
+    let mut entry  = None;
+
+    for module in &modules {
+        let path   = module.path.to_string_lossy().into_owned();
+        let source = read_source(&path)?;
+
+        let mut options = options.clone();
+        if let Some(ref dir) = options.output {
+            let stem = Path::new(dir).join(&module.package).join(&module.name);
+
+            if let Some(parent) = stem.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| Error::Io(format!("could not create `{}`: {}", parent.display(), e)))?
+            }
+            options.output = Some(stem.to_string_lossy().into_owned());
+        }
+
+        if headers {
+            println!("==> {} <==", path)
+        }
+        if options.timings {
+            eprintln!("{} {}", paint(Stream::Stderr, Style::Dim, "timing:"), path)
+        }
+
+        let result = match name {
+            "run" => check(&path, &source, &options),
+            _     => command(&path, &source, &options),
+        };
+
+        match result {
+            Err(Error::Compile(n)) => errors += n,
+            result                 => result?,
+        }
+
+        if module.entry {
+            entry = Some((path, source, options))
+        }
+    }
+
+    match entry {
+        _ if errors > 0                                => Err(Error::Compile(errors)),
+        Some((path, source, options)) if name == "run" => {
+            if options.timings {
+                eprintln!("{} {} (running)", paint(Stream::Stderr, Style::Dim, "timing:"), path)
+            }
+            command(&path, &source, &options)
+        },
+        _                                              => Ok(()),
+    }
+}
 
 fn main() {
-    let mut data = r#"
-let x = 12
-let f(y) = x + y
-let b = true
-let b? = b
-let b!(val: bool) = b = val
-let c = 0x12345678
-let d = 0b10101010
-let s  = r"everything's escaped here \n\t\r means nothing"
-let s? = "non-escaped \"string\" \n\tliteral"
-let char = '\n'
-let not_char = "a"
-let empty = ""
-let float = .42
-let f = 0.1337
-let g = true || false
-    "#.chars();
-
-    let lexer = grab_smaragdine_lexer(&mut data);
-
-    for t in lexer {
-        println!("{}", t)
-    }
-
-    let data2 = r#"
-r"hey"
-'9'
-123.2
-.212
-true
-false
-    "#;
-
-    let mut block_tree = block_tree::BlockTree::new(&data2, 0);
-    let indents        = block_tree.collect_indents();
-
-    let lexed_root = lex_branch(&block_tree.tree(&indents));
-
-    let flat_root = flatten_branch(&lexed_root);
-
-    println!("\n{:#?}", lexed_root);
-    println!("\n{:#?}", flat_root);
-
-    let parser = grab_smaragdine_parser(flat_root);
-
-    for t in parser {
-        println!("{:#?}", t)
+    let mut args = Vec::new();
+
+    // `--color` applies to every command, watch itself included, so it is
+    // taken out before the commands see their arguments
+    let mut own = true;
+    for arg in env::args().skip(1) {
+        own = own && arg != "--";
+
+        if own && arg.starts_with("--color=") {
+            match term::ColorChoice::from_name(&arg["--color=".len()..]) {
+                Some(choice) => term::set_color(choice),
+                None         => usage(),
+            }
+        } else {
+            args.push(arg)
+        }
+    }
+
+    // Commands that don't operate on a single source file
+    let result = match args.get(0).map(|s| s.as_str()) {
+        Some("watch") => match (args.get(1), args.get(2).map(|s| s.as_str())) {
+            (Some(root), Some("--")) if args.len() > 3 => Some(watch::watch(root, &args[3..])),
+            _                                           => usage(),
+        },
+        Some("repl") if args.len() == 1    => Some(repl::repl()),
+        Some("lsp") if args.len() == 1     => Some(lsp::lsp()),
+        Some("serve") if args.len() <= 2   => {
+            Some(serve::serve(args.get(1).map_or(serve::DEFAULT_ADDRESS, |a| a.as_str())))
+        },
+        Some("grammar") => match args.get(1).map(|a| a.as_str()) {
+            None                                                     => Some(grammar("ebnf")),
+            Some(a) if a.starts_with("--format=") && args.len() == 2 => Some(grammar(&a["--format=".len()..])),
+            _                                                        => usage(),
+        },
+        Some("explain") if args.len() <= 2 => Some(explain(args.get(1).map(|a| a.as_str()))),
+        _                                  => None,
+    };
+
+    match result {
+        Some(Err(e)) => fail(e),
+        Some(Ok(())) => return,
+        None         => (),
+    }
+
+    let name = args.get(0).map(|s| s.as_str()).unwrap_or("");
+
+    let command: Command = match name {
+        "tokens"    => tokens,
+        "ast"       => ast,
+        "run"       => run,
+        "build"     => build,
+        "check"     => check,
+        "fmt"       => fmt,
+        "lint"      => lint,
+        "highlight" => highlight,
+        _           => usage(),
+    };
+
+    let mut options = Options::default();
+    let mut path    = None;
+    let mut rest    = args[1..].iter();
+
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--json"           => options.json = true,
+            "--check"          => options.check = true,
+            "-v" | "--timings" => options.timings = true,
+            "-o"               => match rest.next() {
+                Some(output) => options.output = Some(output.clone()),
+                None         => usage(),
+            },
+            a if a.starts_with("--format=") => options.format = Some(a["--format=".len()..].to_string()),
+            a if a.starts_with("--emit=") => {
+                for name in a["--emit=".len()..].split(',') {
+                    match Emit::from_name(name) {
+                        Some(stage) => options.emit.push(stage),
+                        None        => usage(),
+                    }
+                }
+            },
+            a if a.starts_with("--") => usage(),
+            a if path.is_none()      => path = Some(a),
+            _                        => usage(),
+        }
+    }
+
+    let path = path.unwrap_or("-");
+
+    if Path::new(path).is_dir() {
+        let result = match name {
+            "build" | "run" => project(path, name, command, &options),
+            _               => Err(Error::Usage(format!("`{}` is a directory; only build and run take a project", path))),
+        };
+
+        if let Err(e) = result {
+            fail(e)
+        }
+        return
+    }
+
+    let source = match read_source(path) {
+        Ok(source) => source,
+        Err(e)     => fail(e),
+    };
+
+    if let Err(e) = command(path, &source, &options) {
+        fail(e)
     }
 }
@@ -0,0 +1,156 @@
+use Error;
+use config;
+use toml::Value;
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File describing a package, at the root of its project
+pub const MANIFEST: &'static str = "Smaragdine.toml";
+
+/// The contents of a `Smaragdine.toml`:
+///
+/// ```toml
+/// [package]
+/// name    = "hello"
+/// entry   = "src/main.sm"    # the default
+/// sources = ["src"]          # the default
+///
+/// [dependencies]
+/// util = "../util"           # path to another package's root
+/// ```
+#[derive(Debug)]
+pub struct Manifest {
+    pub name:         String,
+    pub entry:        PathBuf,
+    pub sources:      Vec<PathBuf>,
+    pub dependencies: Vec<(String, PathBuf)>,
+}
+
+impl Manifest {
+    /// Reads the manifest of the project at `root`
+    pub fn load(root: &Path) -> Result<Manifest, Error> {
+        let file = root.join(MANIFEST);
+        if !file.is_file() {
+            return Err(Error::Usage(format!("`{}` has no {}", root.display(), MANIFEST)))
+        }
+
+        let invalid = |message: String| Error::Usage(format!("{}: {}", file.display(), message));
+
+        let mut name         = None;
+        let mut entry        = PathBuf::from("src/main.sm");
+        let mut sources      = vec![PathBuf::from("src")];
+        let mut dependencies = Vec::new();
+
+        for (key, value) in config::read(&file)? {
+            match (key.as_str(), value) {
+                ("package.name", Value::String(s))  => name = Some(s),
+                ("package.entry", Value::String(s)) => entry = PathBuf::from(s),
+                ("package.sources", Value::Array(items)) => {
+                    sources.clear();
+                    for item in items {
+                        match item {
+                            Value::String(s) => sources.push(PathBuf::from(s)),
+                            _                => return Err(invalid("`package.sources` must hold strings".to_string())),
+                        }
+                    }
+                },
+                (key, Value::String(s)) if key.starts_with("dependencies.") => {
+                    dependencies.push((key["dependencies.".len()..].to_string(), root.join(s)))
+                },
+                (key, _) => return Err(invalid(format!("invalid setting `{}`", key))),
+            }
+        }
+
+        Ok(Manifest {
+            name:         name.ok_or(invalid("missing `package.name`".to_string()))?,
+            entry:        root.join(entry),
+            sources:      sources.iter().map(|s| root.join(s)).collect(),
+            dependencies: dependencies,
+        })
+    }
+}
+
+/// A source file of a project
+#[derive(Debug)]
+pub struct Module {
+    /// Name of the package the module belongs to
+    pub package: String,
+    pub path:    PathBuf,
+    /// `path` relative to its package root, without the extension
+    pub name:    PathBuf,
+    pub entry:   bool,
+}
+
+/// Collects the modules of the project at `root` and of every package it
+/// depends on, dependencies first.
+pub fn modules(root: &Path) -> Result<Vec<Module>, Error> {
+    let mut modules = Vec::new();
+    let mut visited = HashSet::new();
+
+    collect(root, true, &mut visited, &mut modules)?;
+
+    Ok(modules)
+}
+
+fn collect(root: &Path, top: bool, visited: &mut HashSet<PathBuf>, modules: &mut Vec<Module>) -> Result<(), Error> {
+    let canonical = root.canonicalize()
+                        .map_err(|e| Error::Io(format!("could not read `{}`: {}", root.display(), e)))?;
+
+    // Shared and circular dependencies are only compiled once
+    if !visited.insert(canonical) {
+        return Ok(())
+    }
+
+    let manifest = Manifest::load(root)?;
+
+    for &(ref name, ref path) in &manifest.dependencies {
+        if !path.is_dir() {
+            return Err(Error::Usage(format!("dependency `{}` of `{}` is not a directory: `{}`",
+                                            name, manifest.name, path.display())))
+        }
+        collect(path, false, visited, modules)?;
+    }
+
+    if !manifest.entry.is_file() {
+        return Err(Error::Usage(format!("entry point of `{}` not found: `{}`", manifest.name, manifest.entry.display())))
+    }
+
+    let mut paths = vec![manifest.entry.clone()];
+    for dir in &manifest.sources {
+        sources(dir, &mut paths)?;
+    }
+    paths.sort();
+    paths.dedup();
+
+    for path in paths {
+        let name = path.strip_prefix(root).unwrap_or(&path).with_extension("");
+
+        modules.push(Module {
+            package: manifest.name.clone(),
+            entry:   top && path == manifest.entry,
+            path:    path,
+            name:    name,
+        })
+    }
+
+    Ok(())
+}
+
+/// Adds the `.sm` files beneath `dir` to `paths`
+fn sources(dir: &Path, paths: &mut Vec<PathBuf>) -> Result<(), Error> {
+    let entries = fs::read_dir(dir).map_err(|e| Error::Io(format!("could not read `{}`: {}", dir.display(), e)))?;
+
+    for entry in entries {
+        let path = entry.map_err(|e| Error::Io(format!("could not read `{}`: {}", dir.display(), e)))?.path();
+
+        if path.is_dir() {
+            sources(&path, paths)?
+        } else if path.extension().map_or(false, |e| e == "sm") {
+            paths.push(path)
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,252 @@
+use {lex, parse, render_ast, render_tokens, report, Error, Options};
+
+use libsmac::lexer::{TokenType, KEYWORDS, BOOLEANS};
+
+#[cfg(feature = "readline")]
+use rustyline::{self, Editor};
+#[cfg(feature = "readline")]
+use rustyline::completion::Completer;
+#[cfg(feature = "readline")]
+use rustyline::error::ReadlineError;
+
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+#[cfg(feature = "readline")]
+use std::env;
+#[cfg(not(feature = "readline"))]
+use std::io::{self, BufRead, Write};
+#[cfg(feature = "readline")]
+use std::path::PathBuf;
+use std::rc::Rc;
+
+const HELP: &'static str = "Enter source to parse it. Lines ending in an operator or an open
+bracket, and indented lines, continue the entry until a blank line.
+With the `readline` feature, Tab completes keywords and identifiers
+used so far and Ctrl-C discards the current entry.
+
+commands:
+    :help      show this message
+    :tokens    print the tokens of the last entry
+    :ast       print the parsed tree of the last entry
+    :quit      leave the REPL";
+
+/// Completes keywords and the identifiers seen in earlier entries
+#[cfg(feature = "readline")]
+struct Names {
+    names: Rc<RefCell<BTreeSet<String>>>,
+}
+
+#[cfg(feature = "readline")]
+impl Completer for Names {
+    fn complete(&self, line: &str, pos: usize) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos].char_indices()
+                               .rev()
+                               .take_while(|&(_, c)| c.is_alphanumeric() || "_?!".contains(c))
+                               .last()
+                               .map_or(pos, |(i, _)| i);
+        let prefix = &line[start..pos];
+
+        if prefix.is_empty() {
+            return Ok((pos, Vec::new()))
+        }
+
+        let candidates = self.names
+                             .borrow()
+                             .iter()
+                             .filter(|name| name.starts_with(prefix))
+                             .cloned()
+                             .collect();
+
+        Ok((start, candidates))
+    }
+}
+
+/// `~/.smac_history`, if there is a home directory
+#[cfg(feature = "readline")]
+fn history_path() -> Option<PathBuf> {
+    env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))
+                       .filter(|home| !home.is_empty())
+                       .map(|home| PathBuf::from(home).join(".smac_history"))
+}
+
+/// A line read from the user
+enum Input {
+    Line(String),
+    #[cfg_attr(not(feature = "readline"), allow(dead_code))]
+    Interrupted,
+    Eof,
+}
+
+/// Reads lines with editing, history and completion
+#[cfg(feature = "readline")]
+struct Reader {
+    editor: Editor<Names>,
+}
+
+#[cfg(feature = "readline")]
+impl Reader {
+    fn new(names: Rc<RefCell<BTreeSet<String>>>) -> Reader {
+        let mut editor = Editor::new();
+        editor.set_completer(Some(Names { names: names }));
+
+        if let Some(path) = history_path() {
+            // A missing history file just means a first session
+            let _ = editor.load_history(&path);
+        }
+
+        Reader { editor: editor }
+    }
+
+    fn read(&mut self, prompt: &str) -> Result<Input, Error> {
+        match self.editor.readline(prompt) {
+            Ok(line) => {
+                self.editor.add_history_entry(&line);
+                Ok(Input::Line(line))
+            },
+            Err(ReadlineError::Interrupted) => Ok(Input::Interrupted),
+            Err(ReadlineError::Eof)         => Ok(Input::Eof),
+            Err(e)                          => Err(Error::Io(format!("could not read input: {}", e))),
+        }
+    }
+
+    fn save(&mut self) -> Result<(), Error> {
+        match history_path() {
+            Some(path) => self.editor
+                              .save_history(&path)
+                              .map_err(|e| Error::Io(format!("could not save history to `{}`: {}", path.display(), e))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Reads plain lines from stdin
+#[cfg(not(feature = "readline"))]
+struct Reader {}
+
+#[cfg(not(feature = "readline"))]
+impl Reader {
+    fn new(_names: Rc<RefCell<BTreeSet<String>>>) -> Reader {
+        Reader {}
+    }
+
+    fn read(&mut self, prompt: &str) -> Result<Input, Error> {
+        let fail = |e: io::Error| Error::Io(format!("could not read input: {}", e));
+
+        print!("{}", prompt);
+        io::stdout().flush().map_err(&fail)?;
+
+        let stdin    = io::stdin();
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).map_err(&fail)? == 0 {
+            return Ok(Input::Eof)
+        }
+
+        while line.ends_with('\n') || line.ends_with('\r') {
+            line.pop();
+        }
+        Ok(Input::Line(line))
+    }
+
+    fn save(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+pub fn repl() -> Result<(), Error> {
+    let options  = Options::default();
+    let mut last = String::new();
+
+    let names = Rc::new(RefCell::new(BTreeSet::new()));
+    for name in KEYWORDS.iter().chain(BOOLEANS) {
+        names.borrow_mut().insert(name.to_string());
+    }
+    let mut reader = Reader::new(names.clone());
+
+    println!("Smaragdine REPL, :help for help");
+
+    loop {
+        let entry = match read_entry(&mut reader)? {
+            Some(entry) => entry,
+            None        => break,
+        };
+
+        match entry.trim() {
+            ""                      => (),
+            ":help"                 => println!("{}", HELP),
+            ":quit" | ":q"          => break,
+            ":tokens"               => print!("{}", render_tokens(&lex(&last, &options).0, &options)),
+            ":ast"                  => print!("{}", render_ast(&parse(&last, &options).0, &options)),
+            c if c.starts_with(':') => println!("unknown command `{}`, try :help", c),
+            _                       => {
+                let (nodes, diagnostics) = parse(&entry, &options);
+
+                print!("{}", render_ast(&nodes, &options));
+                let _ = report("<repl>", &diagnostics);
+
+                for t in lex(&entry, &options).0 {
+                    if *t.token_type() == TokenType::Identifier {
+                        names.borrow_mut().insert(t.content().clone());
+                    }
+                }
+
+                last = entry
+            },
+        }
+    }
+
+    reader.save()
+}
+
+/// Reads lines until the entry is complete, or `None` at end of input.
+fn read_entry(reader: &mut Reader) -> Result<Option<String>, Error> {
+    let mut entry  = String::new();
+    let mut prompt = ">> ";
+
+    loop {
+        let line = match reader.read(prompt)? {
+            Input::Line(line)  => line,
+            Input::Interrupted => {
+                entry.clear();
+                prompt = ">> ";
+                continue
+            },
+            Input::Eof => {
+                return Ok(if entry.is_empty() { None } else { Some(entry) })
+            },
+        };
+
+        let blank = line.trim().is_empty();
+        entry.push_str(&line);
+        entry.push('\n');
+
+        if blank || !incomplete(&entry) {
+            return Ok(Some(entry))
+        }
+        prompt = ".. ";
+    }
+}
+
+/// Whether `entry` expects more lines: it ends in an operator or `->`,
+/// has unclosed brackets, or its last line is indented.
+fn incomplete(entry: &str) -> bool {
+    let (tokens, _) = lex(entry, &Options::default());
+    let mut depth   = 0;
+
+    for t in &tokens {
+        if *t.token_type() == TokenType::Symbol {
+            match t.content().as_str() {
+                "(" | "[" | "{" => depth += 1,
+                ")" | "]" | "}" => depth -= 1,
+                _               => (),
+            }
+        }
+    }
+
+    let dangling = match tokens.last() {
+        Some(t) => *t.token_type() == TokenType::Operator || t.content() == "->",
+        None    => false,
+    };
+    let indented = entry.lines().last().map_or(false, |l| l.starts_with(' '));
+
+    depth > 0 || dangling || indented
+}
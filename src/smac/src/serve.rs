@@ -0,0 +1,304 @@
+use Error;
+use json;
+
+use libsmac::lexer::{Token, TokenType};
+use libsmac::parser::{lex_source, parse_tokens};
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Address served when none is given
+pub const DEFAULT_ADDRESS: &'static str = "127.0.0.1:8080";
+
+/// Largest request line and headers accepted, in bytes
+const MAX_HEAD: u64 = 8 << 10;
+
+/// Most headers accepted
+const MAX_HEADERS: usize = 64;
+
+/// Largest source accepted, in bytes. Lexing and parsing are linear and
+/// brackets nest at most 256 deep, so this bounds the time and stack a
+/// compile needs.
+const MAX_BODY: usize = 64 << 10;
+
+/// How long a client may take to send its whole request, and then to
+/// receive the whole response, in seconds
+const TIMEOUT: u64 = 5;
+
+/// How long a response waits for its compile, in seconds. A compile can't
+/// be stopped, so one that runs over keeps going in the background.
+const COMPILE_TIMEOUT: u64 = 5;
+
+/// Most connections handled at once, counting those whose compile is
+/// still running in the background. Others are turned away with a 503.
+const MAX_CONNECTIONS: usize = 16;
+
+/// Stack of the thread compiling a source; indented blocks still recurse
+const COMPILE_STACK: usize = 8 << 20;
+
+const INDEX: &'static str = "POST Smaragdine source to /compile to receive its tokens, AST and
+diagnostics as JSON.
+";
+
+/// Serves the playground API on `address`, handling each connection on
+/// its own thread, up to `MAX_CONNECTIONS` at once.
+pub fn serve(address: &str) -> Result<(), Error> {
+    let listener = TcpListener::bind(address)
+        .map_err(|e| Error::Io(format!("could not listen on `{}`: {}", address, e)))?;
+
+    eprintln!("listening on http://{}", address);
+
+    let connections = Arc::new(AtomicUsize::new(0));
+
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(e)     => {
+                eprintln!("error: could not accept connection: {}", e);
+                continue
+            },
+        };
+
+        let slot = match Slot::take(&connections) {
+            Some(slot) => Arc::new(slot),
+            None       => {
+                // The response fits in the socket's buffer, so this doesn't wait on the client
+                let _ = stream.set_write_timeout(Some(Duration::from_secs(1)));
+                let _ = respond(&mut stream, "503 Service Unavailable", "text/plain", "too many connections, try again later\n");
+                continue
+            },
+        };
+
+        thread::spawn(move || {
+            if let Err(e) = handle(stream, slot) {
+                eprintln!("error: {}", e)
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// One of the `MAX_CONNECTIONS` connections, given back when dropped
+struct Slot {
+    connections: Arc<AtomicUsize>,
+}
+
+impl Slot {
+    fn take(connections: &Arc<AtomicUsize>) -> Option<Slot> {
+        if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            connections.fetch_sub(1, Ordering::SeqCst);
+            return None
+        }
+
+        Some(Slot { connections: connections.clone() })
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.connections.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// The client's side of a connection, which fails reads and writes once
+/// `deadline` has passed however slowly the client sends or receives
+struct Client {
+    stream:   TcpStream,
+    deadline: Instant,
+}
+
+impl Client {
+    /// How long is left before the deadline
+    fn remaining(&self) -> io::Result<Duration> {
+        let now = Instant::now();
+        if now >= self.deadline {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "the client took too long"))
+        }
+
+        Ok(self.deadline - now)
+    }
+}
+
+/// Turns the socket's timeouts into errors saying what ran out
+fn late(e: io::Error) -> io::Error {
+    match e.kind() {
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => io::Error::new(io::ErrorKind::TimedOut, "the client took too long"),
+        _                                                   => e,
+    }
+}
+
+impl Write for Client {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let remaining = self.remaining()?;
+
+        self.stream.set_write_timeout(Some(remaining))?;
+        self.stream.write(buf).map_err(late)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl Read for Client {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.remaining()?;
+
+        self.stream.set_read_timeout(Some(remaining))?;
+        self.stream.read(buf).map_err(late)
+    }
+}
+
+fn handle(stream: TcpStream, slot: Arc<Slot>) -> Result<(), String> {
+    let mut reader = BufReader::new(Client {
+        stream:   stream,
+        deadline: Instant::now() + Duration::from_secs(TIMEOUT),
+    });
+
+    let (status, content_type, body) = match read_head(&mut reader)? {
+        None => ("431 Request Header Fields Too Large", "text/plain",
+                 format!("requests are limited to {} headers and {} bytes before the body\n", MAX_HEADERS, MAX_HEAD)),
+
+        Some((method, target, length)) => match (method.as_str(), target.as_str()) {
+            ("GET", "/") => ("200 OK", "text/plain", INDEX.to_string()),
+
+            ("POST", "/compile") => match length {
+                None                    => ("411 Length Required", "text/plain", "missing Content-Length\n".to_string()),
+                Some(n) if n > MAX_BODY => ("413 Payload Too Large", "text/plain", format!("sources are limited to {} bytes\n", MAX_BODY)),
+                Some(n) => {
+                    let mut body = vec![0; n];
+                    reader.read_exact(&mut body).map_err(|e| e.to_string())?;
+
+                    match String::from_utf8(body).map(|source| compile_in_time(source, slot)) {
+                        Ok(Some(json)) => ("200 OK", "application/json", json),
+                        Ok(None)       => ("503 Service Unavailable", "text/plain", "the source could not be compiled in time\n".to_string()),
+                        Err(_)         => ("400 Bad Request", "text/plain", "source is not UTF-8\n".to_string()),
+                    }
+                },
+            },
+
+            (_, "/") | (_, "/compile") => ("405 Method Not Allowed", "text/plain", "method not allowed\n".to_string()),
+            _                          => ("404 Not Found", "text/plain", "not found\n".to_string()),
+        },
+    };
+
+    let client      = reader.get_mut();
+    client.deadline = Instant::now() + Duration::from_secs(TIMEOUT);
+
+    respond(client, status, content_type, &body).map_err(|e| e.to_string())
+}
+
+fn respond<W: Write>(out: &mut W, status: &str, content_type: &str, body: &str) -> io::Result<()> {
+    write!(out,
+           "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+           status, content_type, body.len(), body)
+}
+
+/// The method, target and Content-Length of the request, or `None` if its
+/// request line and headers are over the limits
+fn read_head<R: BufRead>(reader: &mut R) -> Result<Option<(String, String, Option<usize>)>, String> {
+    let mut head  = reader.take(MAX_HEAD);
+    let mut lines = Vec::new();
+
+    loop {
+        let mut line = String::new();
+        head.read_line(&mut line).map_err(|e| e.to_string())?;
+
+        // A line cut short by the limit, rather than by the client closing
+        // the connection, means the head is too long
+        if !line.ends_with('\n') && head.limit() == 0 {
+            return Ok(None)
+        }
+        if line.trim().is_empty() {
+            break
+        }
+        if lines.len() > MAX_HEADERS {
+            return Ok(None)
+        }
+
+        lines.push(line)
+    }
+
+    let mut lines  = lines.iter();
+    let mut length = None;
+
+    let mut parts = lines.next().map_or("", |l| l.as_str()).split_whitespace();
+    let method    = parts.next().unwrap_or("").to_string();
+    let target    = parts.next().unwrap_or("").to_string();
+
+    for header in lines {
+        let mut parts = header.trim().splitn(2, ':');
+        if parts.next().map_or(false, |name| name.trim().eq_ignore_ascii_case("content-length")) {
+            length = parts.next().and_then(|n| n.trim().parse::<usize>().ok());
+        }
+    }
+
+    Ok(Some((method, target, length)))
+}
+
+/// Compiles `source` on a thread of its own, giving `None` if that fails
+/// or doesn't finish within `COMPILE_TIMEOUT`. The thread holds on to
+/// `slot` until the compile ends, even if nothing waits for it any more.
+fn compile_in_time(source: String, slot: Arc<Slot>) -> Option<String> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::Builder::new()
+        .stack_size(COMPILE_STACK)
+        .spawn(move || {
+            let _ = sender.send(compile(&source));
+            drop(slot)
+        })
+        .ok()?;
+
+    receiver.recv_timeout(Duration::from_secs(COMPILE_TIMEOUT)).ok()
+}
+
+/// Serialises `tokens` onto `out`, listing the tokens of each block in
+/// its place
+fn list_tokens(tokens: &[Token], out: &mut Vec<String>) {
+    for t in tokens {
+        match *t.token_type() {
+            TokenType::Block(ref inner) => list_tokens(inner, out),
+            _                           => out.push(json::token(t)),
+        }
+    }
+}
+
+/// `{"tokens": [...], "ast": [...], "diagnostics": [...]}` for `source`.
+/// There is no interpreter yet, so nothing is run.
+fn compile(source: &str) -> String {
+    let (tokens, lexing) = lex_source(source);
+    let mut listed       = Vec::new();
+    list_tokens(&tokens, &mut listed);
+
+    let (nodes, diagnostics) = parse_tokens(tokens, lexing);
+    let nodes: Vec<_>        = nodes.iter().map(json::node).collect();
+    let diagnostics: Vec<_>  = diagnostics.iter().map(json::diagnostic).collect();
+
+    json::object(&[("tokens", json::array(&listed)),
+                   ("ast", json::array(&nodes)),
+                   ("diagnostics", json::array(&diagnostics))]) + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_head, MAX_HEADERS};
+
+    #[test]
+    fn read_head_limits() {
+        let head = |text: &str| read_head(&mut text.as_bytes()).unwrap();
+
+        assert_eq!(head("POST /compile HTTP/1.1\r\ncontent-length: 12\r\n\r\nbody"),
+                   Some(("POST".to_string(), "/compile".to_string(), Some(12))));
+        assert_eq!(head("GET / HTTP/1.1\r\n"), Some(("GET".to_string(), "/".to_string(), None)));
+        assert_eq!(head(&format!("GET / HTTP/1.1\r\nX: {}\r\n\r\n", "a".repeat(10_000))), None);
+        assert_eq!(head(&format!("GET / HTTP/1.1\r\n{}\r\n", "X: a\r\n".repeat(MAX_HEADERS + 1))), None);
+        assert!(head(&format!("GET / HTTP/1.1\r\n{}\r\n", "X: a\r\n".repeat(MAX_HEADERS))).is_some());
+    }
+}
@@ -0,0 +1,135 @@
+use libsmac::diagnostic::{Diagnostic, Severity};
+
+use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// When to color output, as given by `--color`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn from_name(name: &str) -> Option<ColorChoice> {
+        match name {
+            "auto"   => Some(ColorChoice::Auto),
+            "always" => Some(ColorChoice::Always),
+            "never"  => Some(ColorChoice::Never),
+            _        => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ColorChoice::Auto   => "auto",
+            ColorChoice::Always => "always",
+            ColorChoice::Never  => "never",
+        }
+    }
+}
+
+/// The choice for the whole process, set once from the arguments
+static CHOICE: AtomicUsize = AtomicUsize::new(ColorChoice::Auto as usize);
+
+pub fn set_color(choice: ColorChoice) {
+    CHOICE.store(choice as usize, Ordering::Relaxed)
+}
+
+pub fn color() -> ColorChoice {
+    match CHOICE.load(Ordering::Relaxed) {
+        c if c == ColorChoice::Always as usize => ColorChoice::Always,
+        c if c == ColorChoice::Never as usize  => ColorChoice::Never,
+        _                                      => ColorChoice::Auto,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+#[cfg(unix)]
+fn is_terminal(stream: Stream) -> bool {
+    use std::os::raw::c_int;
+
+    extern "C" {
+        fn isatty(fd: c_int) -> c_int;
+    }
+
+    let fd = match stream {
+        Stream::Stdout => 1,
+        Stream::Stderr => 2,
+    };
+
+    unsafe { isatty(fd) == 1 }
+}
+
+#[cfg(not(unix))]
+fn is_terminal(_stream: Stream) -> bool {
+    false
+}
+
+/// Whether output to `stream` is colored. With `--color=auto` it is
+/// when the stream is a terminal and `NO_COLOR` is unset or empty.
+pub fn colored(stream: Stream) -> bool {
+    match color() {
+        ColorChoice::Always => true,
+        ColorChoice::Never  => false,
+        ColorChoice::Auto   => env::var_os("NO_COLOR").map_or(true, |v| v.is_empty()) && is_terminal(stream),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Style {
+    Bold,
+    Dim,
+    Red,
+    Green,
+    Yellow,
+    Magenta,
+    Cyan,
+}
+
+impl Style {
+    fn code(&self) -> &'static str {
+        match *self {
+            Style::Bold    => "1",
+            Style::Dim     => "2",
+            Style::Red     => "1;31",
+            Style::Green   => "1;32",
+            Style::Yellow  => "1;33",
+            Style::Magenta => "35",
+            Style::Cyan    => "36",
+        }
+    }
+}
+
+/// `text` in `style`, whether or not output is colored
+fn style(style: Style, text: &str) -> String {
+    format!("\x1b[{}m{}\x1b[0m", style.code(), text)
+}
+
+/// `text` in `style`, if output to `stream` is colored
+pub fn paint(stream: Stream, style: Style, text: &str) -> String {
+    if colored(stream) {
+        self::style(style, text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Renders a diagnostic in the input called `name` for stderr, as
+/// `name:line:col: severity: message`
+pub fn diagnostic(name: &str, d: &Diagnostic) -> String {
+    let location = format!("{}:{}:{}:", name, d.position().line, d.position().col + 1);
+    let code     = d.code().map_or(String::new(), |c| format!("[{}]", c.id()));
+    let severity = match d.severity() {
+        Severity::Error   => paint(Stream::Stderr, Style::Red, &format!("error{}:", code)),
+        Severity::Warning => paint(Stream::Stderr, Style::Yellow, &format!("warning{}:", code)),
+    };
+
+    format!("{} {} {}", paint(Stream::Stderr, Style::Bold, &location), severity, d.message())
+}
@@ -0,0 +1,194 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// A value in the subset of TOML that smac reads
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+/// Parses a subset of TOML: `[table]` headers, `#` comments, and
+/// `key = value` pairs whose values are strings, integers, booleans or
+/// single-line arrays of those. Keys are qualified by their table,
+/// as in `table.key`.
+pub fn parse(text: &str) -> Result<Vec<(String, Value)>, String> {
+    let mut pairs: Vec<(String, Value)> = Vec::new();
+    let mut tables = Vec::new();
+    let mut table  = String::new();
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue
+        }
+
+        if line.starts_with("[[") {
+            return Err(format!("line {}: arrays of tables are not supported", number + 1))
+        }
+
+        if line.starts_with('[') {
+            let end  = line.find(']').ok_or(format!("line {}: unclosed table header", number + 1))?;
+            let rest = line[end + 1..].trim();
+            table    = line[1..end].trim().to_string();
+
+            if !rest.is_empty() && !rest.starts_with('#') {
+                return Err(format!("line {}: unexpected `{}` after table header", number + 1, rest))
+            }
+            if table.split('.').any(|part| !is_key(part)) {
+                return Err(format!("line {}: invalid table name `{}`", number + 1, table))
+            }
+            if tables.contains(&table) {
+                return Err(format!("line {}: table `{}` is defined twice", number + 1, table))
+            }
+
+            tables.push(table.clone());
+            continue
+        }
+
+        let equals = line.find('=').ok_or(format!("line {}: expected `key = value`", number + 1))?;
+        let key    = line[..equals].trim();
+
+        if !is_key(key) {
+            return Err(format!("line {}: invalid key `{}`", number + 1, key))
+        }
+
+        let mut chars = line[equals + 1..].chars().peekable();
+        let value     = parse_value(&mut chars).map_err(|e| format!("line {}: {}", number + 1, e))?;
+
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            None | Some('#') => (),
+            Some(c)          => return Err(format!("line {}: unexpected `{}` after value", number + 1, c)),
+        }
+
+        let key = if table.is_empty() { key.to_string() } else { format!("{}.{}", table, key) };
+        if pairs.iter().any(|&(ref k, _)| *k == key) {
+            return Err(format!("line {}: duplicate key `{}`", number + 1, key))
+        }
+        pairs.push((key, value))
+    }
+
+    Ok(pairs)
+}
+
+/// Whether `key` is a bare TOML key
+fn is_key(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || "_-".contains(c))
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.peek().map_or(false, |c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+fn parse_value(chars: &mut Peekable<Chars>) -> Result<Value, String> {
+    skip_whitespace(chars);
+
+    match chars.peek().cloned() {
+        Some('"') => {
+            chars.next();
+
+            let mut string = String::new();
+            loop {
+                match chars.next() {
+                    Some('"')  => return Ok(Value::String(string)),
+                    Some('\\') => match chars.next() {
+                        Some('n')  => string.push('\n'),
+                        Some('t')  => string.push('\t'),
+                        Some('"')  => string.push('"'),
+                        Some('\\') => string.push('\\'),
+                        Some(c)    => return Err(format!("invalid escape `\\{}`", c)),
+                        None       => return Err("unterminated string".to_string()),
+                    },
+                    Some(c)    => string.push(c),
+                    None       => return Err("unterminated string".to_string()),
+                }
+            }
+        },
+
+        Some('[') => {
+            chars.next();
+
+            let mut items = Vec::new();
+            loop {
+                skip_whitespace(chars);
+                if chars.peek() == Some(&']') {
+                    chars.next();
+                    return Ok(Value::Array(items))
+                }
+
+                items.push(parse_value(chars)?);
+
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => (),
+                    Some(']') => return Ok(Value::Array(items)),
+                    _         => return Err("expected `,` or `]` in array".to_string()),
+                }
+            }
+        },
+
+        Some(_) => {
+            let mut word = String::new();
+            while chars.peek().map_or(false, |&c| c.is_alphanumeric() || "+-_".contains(c)) {
+                word.push(chars.next().unwrap());
+            }
+
+            match word.as_str() {
+                "true"  => Ok(Value::Boolean(true)),
+                "false" => Ok(Value::Boolean(false)),
+                _       => word.replace('_', "")
+                               .parse::<i64>()
+                               .map(Value::Integer)
+                               .map_err(|_| format!("invalid value `{}`", word)),
+            }
+        },
+
+        None => Err("missing value".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Value};
+
+    #[test]
+    fn parse_tables_and_values() {
+        let text = "# settings\nname = \"a\\tb\" # trailing\n\n[deps]\nlist = [1, -2_000, true, [\"x\"],]\nflag = false\n";
+
+        assert_eq!(parse(text), Ok(vec![
+            ("name".to_string(), Value::String("a\tb".to_string())),
+            ("deps.list".to_string(), Value::Array(vec![Value::Integer(1),
+                                                        Value::Integer(-2000),
+                                                        Value::Boolean(true),
+                                                        Value::Array(vec![Value::String("x".to_string())])])),
+            ("deps.flag".to_string(), Value::Boolean(false)),
+        ]));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |text: &str| parse(text).unwrap_err();
+
+        assert_eq!(error("[[deps]]\n"), "line 1: arrays of tables are not supported");
+        assert_eq!(error("[deps] x = 1\n"), "line 1: unexpected `x = 1` after table header");
+        assert_eq!(error("[deps\n"), "line 1: unclosed table header");
+        assert_eq!(error("[a b]\n"), "line 1: invalid table name `a b`");
+        assert_eq!(error("[a]\n[b]\n[a]\n"), "line 3: table `a` is defined twice");
+        assert_eq!(error("a = 1\nb = 2\na = 3\n"), "line 3: duplicate key `a`");
+        assert_eq!(error("[t]\na = 1\na = 2\n"), "line 3: duplicate key `t.a`");
+        assert_eq!(error("a b = 1\n"), "line 1: invalid key `a b`");
+        assert_eq!(error("a\n"), "line 1: expected `key = value`");
+        assert_eq!(error("a =\n"), "line 1: missing value");
+        assert_eq!(error("a = \"open\n"), "line 1: unterminated string");
+        assert_eq!(error("a = \"\\q\"\n"), "line 1: invalid escape `\\q`");
+        assert_eq!(error("a = [1 2]\n"), "line 1: expected `,` or `]` in array");
+        assert_eq!(error("a = yes\n"), "line 1: invalid value `yes`");
+        assert_eq!(error("a = 1 2\n"), "line 1: unexpected `2` after value");
+    }
+}
@@ -0,0 +1,126 @@
+use Error;
+use term::{self, paint, Stream, Style};
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How often the watched files are polled, in milliseconds
+const POLL_INTERVAL: u64 = 250;
+
+/// How long the files must stay unchanged before rerunning, in milliseconds.
+/// Editors often save in several steps; this keeps them from triggering
+/// more than one run.
+const DEBOUNCE: u64 = 100;
+
+type Snapshot = Vec<(PathBuf, Option<SystemTime>)>;
+
+/// Reruns `smac <command>` every time a file under `root` changes.
+pub fn watch(root: &str, command: &[String]) -> Result<(), Error> {
+    let exe  = env::current_exe().map_err(|e| Error::Io(format!("could not locate smac: {}", e)))?;
+    let root = Path::new(root);
+
+    if !root.exists() {
+        return Err(Error::Io(format!("could not watch `{}`: no such file or directory", root.display())))
+    }
+
+    let mut last = snapshot(root);
+
+    loop {
+        rerun(&exe, command);
+        last = wait_for_change(root, &last);
+    }
+}
+
+/// Blocks until the files under `root` differ from `last` and have
+/// settled, returning their new state.
+fn wait_for_change(root: &Path, last: &Snapshot) -> Snapshot {
+    let mut current = snapshot(root);
+
+    while current == *last {
+        thread::sleep(Duration::from_millis(POLL_INTERVAL));
+        current = snapshot(root);
+    }
+
+    loop {
+        thread::sleep(Duration::from_millis(DEBOUNCE));
+
+        let next = snapshot(root);
+        if next == current {
+            return current
+        }
+        current = next
+    }
+}
+
+fn rerun(exe: &Path, command: &[String]) {
+    // Clear the screen and move the cursor home
+    print!("\x1b[2J\x1b[H");
+    println!("{} smac {}\n", stamp(), command.join(" "));
+
+    // The child shares our terminal, so it colors as we were told to
+    let color = format!("--color={}", term::color().name());
+
+    match Command::new(exe).arg(color).args(command).status() {
+        Ok(status) => match status.code() {
+            Some(0)    => println!("\n{} {}", stamp(), paint(Stream::Stdout, Style::Green, "finished")),
+            Some(code) => println!("\n{} {} with exit status {}", stamp(), paint(Stream::Stdout, Style::Red, "failed"), code),
+            None       => println!("\n{} {} by signal", stamp(), paint(Stream::Stdout, Style::Red, "terminated")),
+        },
+        Err(e) => println!("{} {}: {}", stamp(), paint(Stream::Stdout, Style::Red, "could not run smac"), e),
+    }
+}
+
+/// The current time as a dimmed `[HH:MM:SS UTC]`
+fn stamp() -> String {
+    paint(Stream::Stdout, Style::Dim, &format!("[{}]", timestamp()))
+}
+
+/// Modification times of `root`, or of every `.sm` file beneath it
+fn snapshot(root: &Path) -> Snapshot {
+    let mut files = Vec::new();
+
+    collect(root, &mut files);
+    files.sort();
+
+    files.into_iter()
+         .map(|path| {
+             let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+             (path, modified)
+         })
+         .collect()
+}
+
+fn collect(path: &Path, files: &mut Vec<PathBuf>) {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return
+    }
+
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_)      => return,
+    };
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+
+        if path.is_dir() {
+            collect(&path, files)
+        } else if path.extension().map_or(false, |e| e == "sm") {
+            files.push(path)
+        }
+    }
+}
+
+fn timestamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    format!("{:02}:{:02}:{:02} UTC", secs / 3600 % 24, secs / 60 % 60, secs % 60)
+}